@@ -1,44 +1,141 @@
 //! User authentication and profile management
 
+use crate::crypto::*;
+use crate::gamesave::chrono_timestamp;
+use crate::gamestatus::GameResult;
+use crate::rating::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
 const USERS_FILE: &str = ".chess_users.json";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub username: String,
-    #[serde(skip_serializing)]
+    /// PBKDF2-HMAC-SHA256 of the password (hex), or the old unsalted hash for
+    /// records that have not been migrated yet
+    #[serde(default)]
     password_hash: String,
+    /// Per-user random salt (hex); empty for records from before salting
+    #[serde(default)]
+    salt: String,
+    #[serde(default)]
+    kdf_iterations: u32,
     pub games_played: u32,
     pub games_won: u32,
     pub games_drawn: u32,
     pub games_lost: u32,
     pub rating: u32,
+    /// Rated games, oldest first
+    #[serde(default)]
+    pub rating_history: Vec<RatingChange>,
 }
 
 impl User {
-    pub fn new(username: String, password: String) -> Self {
-        User {
+    pub fn new(username: String, password: String, iterations: u32) -> Self {
+        let mut user = User {
             username,
-            password_hash: Self::hash_password(&password),
+            password_hash: String::new(),
+            salt: String::new(),
+            kdf_iterations: 0,
             games_played: 0,
             games_won: 0,
             games_drawn: 0,
             games_lost: 0,
             rating: 1200,
-        }
+            rating_history: Vec::new(),
+        };
+        user.set_password(&password, iterations);
+        user
+    }
+
+    /// Hash the password with a fresh salt and `iterations` rounds of PBKDF2
+    pub fn set_password(&mut self, password: &str, iterations: u32) {
+        let salt = random_bytes(SALT_LEN);
+        self.password_hash = to_hex(&Self::hash_password(password, &salt, iterations));
+        self.salt = to_hex(&salt);
+        self.kdf_iterations = iterations;
     }
 
-    fn hash_password(password: &str) -> String {
-        // Simple hash for demonstration (in production, use bcrypt or argon2)
-        format!("{:x}", md5_hash(password.as_bytes()))
+    fn hash_password(password: &str, salt: &[u8], iterations: u32) -> [u8; DIGEST_LEN] {
+        let mut hash = [0u8; DIGEST_LEN];
+        pbkdf2_hmac_sha256(password.as_bytes(), salt, iterations, &mut hash);
+        hash
+    }
+
+    /// Whether the record holds a hash a password can be checked against.
+    /// Records written while the hash was left out of the users file don't.
+    pub fn has_password(&self) -> bool {
+        !self.password_hash.is_empty()
     }
 
     pub fn verify_password(&self, password: &str) -> bool {
-        self.password_hash == Self::hash_password(password)
+        if !self.has_password() {
+            return false;
+        }
+        if self.salt.is_empty() {
+            // Records from before salting hold the old unsalted hash
+            let legacy = format!("{:x}", md5_hash(password.as_bytes()));
+            return constant_time_eq(legacy.as_bytes(), self.password_hash.as_bytes());
+        }
+
+        match (from_hex(&self.salt), from_hex(&self.password_hash)) {
+            (Some(salt), Some(stored)) => {
+                let hash = Self::hash_password(password, &salt, self.kdf_iterations);
+                constant_time_eq(&hash, &stored)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the stored hash is weaker than `iterations` rounds of salted PBKDF2
+    pub fn needs_rehash(&self, iterations: u32) -> bool {
+        self.salt.is_empty() || self.kdf_iterations < iterations
+    }
+
+    /// Count a finished game the user played as `color`. Games against a rated
+    /// opponent (name and rating) also update the rating and its history.
+    pub fn record_game(&mut self, result: GameResult, color: u8, opponent: Option<(&str, u32)>) {
+        if let Some((name, opponent_rating)) = opponent {
+            let score = result.score_for(color);
+            let before = self.rating;
+            self.rating = updated_rating(before, opponent_rating, score, self.games_played);
+            self.rating_history.push(RatingChange {
+                timestamp: chrono_timestamp(),
+                opponent: name.to_string(),
+                opponent_rating,
+                score,
+                before,
+                after: self.rating,
+            });
+        }
+
+        self.games_played += 1;
+        match result.score_for(color) {
+            s if s > 0.75 => self.games_won += 1,
+            s if s > 0.25 => self.games_drawn += 1,
+            _ => self.games_lost += 1,
+        }
+    }
+
+    /// Reverse `record_game`, e.g. when the final move is taken back
+    pub fn unrecord_game(&mut self, result: GameResult, color: u8, rated: bool) {
+        if rated {
+            if let Some(change) = self.rating_history.pop() {
+                self.rating = change.before;
+            }
+        }
+
+        self.games_played = self.games_played.saturating_sub(1);
+        match result.score_for(color) {
+            s if s > 0.75 => self.games_won = self.games_won.saturating_sub(1),
+            s if s > 0.25 => self.games_drawn = self.games_drawn.saturating_sub(1),
+            _ => self.games_lost = self.games_lost.saturating_sub(1),
+        }
     }
 
     pub fn win_rate(&self) -> f32 {
@@ -50,7 +147,7 @@ impl User {
     }
 }
 
-// Simple MD5-like hash for demonstration
+// Old unsalted hash, only used to verify records written before PBKDF2
 fn md5_hash(data: &[u8]) -> u64 {
     let mut hash = 0x123456789ABCDEFu64;
     for &byte in data {
@@ -62,6 +159,8 @@ fn md5_hash(data: &[u8]) -> u64 {
 pub struct AuthManager {
     users: HashMap<String, User>,
     users_file: PathBuf,
+    /// PBKDF2 rounds for new hashes; older hashes are upgraded on login
+    kdf_iterations: u32,
 }
 
 impl AuthManager {
@@ -69,6 +168,7 @@ impl AuthManager {
         let mut manager = AuthManager {
             users: HashMap::new(),
             users_file: PathBuf::from(USERS_FILE),
+            kdf_iterations: PBKDF2_ITERATIONS,
         };
         manager.load_users();
         manager
@@ -99,30 +199,56 @@ impl AuthManager {
             return Err("Username must be at least 3 characters".to_string());
         }
 
-        if password.len() < 4 {
-            return Err("Password must be at least 4 characters".to_string());
-        }
+        check_password_length(&password)?;
 
         if self.users.contains_key(&username) {
             return Err("Username already exists".to_string());
         }
 
-        let user = User::new(username.clone(), password);
+        let user = User::new(username.clone(), password, self.kdf_iterations);
         self.users.insert(username.clone(), user.clone());
         self.save_users();
         Ok(user)
     }
 
-    pub fn login(&self, username: &str, password: &str) -> Result<User, String> {
-        if let Some(user) = self.users.get(username) {
-            if user.verify_password(password) {
-                Ok(user.clone())
-            } else {
-                Err("Invalid password".to_string())
-            }
-        } else {
-            Err("User not found".to_string())
+    /// Check the password, upgrading records with an outdated hash on success
+    pub fn login(&mut self, username: &str, password: &str) -> Result<User, String> {
+        let user = match self.users.get_mut(username) {
+            Some(user) => user,
+            None => return Err("User not found".to_string()),
+        };
+
+        if !user.has_password() {
+            return Err(format!(
+                "This account has no stored password. Set one with: chess_engine reset-password {}",
+                username
+            ));
         }
+        if !user.verify_password(password) {
+            return Err("Invalid password".to_string());
+        }
+
+        if user.needs_rehash(self.kdf_iterations) {
+            user.set_password(password, self.kdf_iterations);
+            let user = user.clone();
+            self.save_users();
+            return Ok(user);
+        }
+        Ok(user.clone())
+    }
+
+    /// Give an account written without a password hash a password, keeping its
+    /// statistics and rating. Accounts that already have a password are refused,
+    /// so this can't be used to take one over.
+    pub fn reset_password(&mut self, username: &str, password: &str) -> Result<(), String> {
+        check_password_length(password)?;
+        let user = self.users.get_mut(username).ok_or("User not found")?;
+        if user.has_password() {
+            return Err(format!("{} already has a password; log in to use the account", username));
+        }
+        user.set_password(password, self.kdf_iterations);
+        self.save_users();
+        Ok(())
     }
 
     pub fn update_user(&mut self, user: &User) {
@@ -135,8 +261,133 @@ impl AuthManager {
     }
 }
 
+fn check_password_length(password: &str) -> Result<(), String> {
+    if password.len() < 4 {
+        return Err("Password must be at least 4 characters".to_string());
+    }
+    Ok(())
+}
+
 impl Default for AuthManager {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BLACK, WHITE};
+
+    fn legacy_user(password_hash: &str) -> User {
+        User {
+            username: "legacy".to_string(),
+            password_hash: password_hash.to_string(),
+            salt: String::new(),
+            kdf_iterations: 0,
+            games_played: 0,
+            games_won: 0,
+            games_drawn: 0,
+            games_lost: 0,
+            rating: 1200,
+            rating_history: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_password_hashing() {
+        let mut user = legacy_user("");
+        user.set_password("secret", 10);
+        assert!(user.verify_password("secret"));
+        assert!(!user.verify_password("Secret"));
+        assert_eq!(user.salt.len(), SALT_LEN * 2);
+
+        // Same password, different salt, different hash
+        let mut other = legacy_user("");
+        other.set_password("secret", 10);
+        assert_ne!(user.salt, other.salt);
+        assert_ne!(user.password_hash, other.password_hash);
+
+        // The hash is persisted
+        let json = serde_json::to_string(&user).unwrap();
+        let loaded: User = serde_json::from_str(&json).unwrap();
+        assert!(loaded.verify_password("secret"));
+        assert!(!loaded.needs_rehash(10));
+        assert!(loaded.needs_rehash(PBKDF2_ITERATIONS));
+    }
+
+    #[test]
+    fn test_legacy_records() {
+        let old_hash = format!("{:x}", md5_hash(b"secret"));
+        let user = legacy_user(&old_hash);
+        assert!(user.needs_rehash(10));
+        assert!(user.verify_password("secret"));
+        assert!(!user.verify_password("wrong"));
+
+        // Records written without any hash deserialize, but accept no password
+        let json = r#"{"username":"old","games_played":3,"games_won":1,"games_drawn":1,"games_lost":1,"rating":1210}"#;
+        let user: User = serde_json::from_str(json).unwrap();
+        assert!(!user.has_password());
+        assert!(!user.verify_password(""));
+        assert!(!user.verify_password("anything"));
+        assert_eq!(user.games_played, 3);
+        assert!(user.rating_history.is_empty());
+    }
+
+    /// A manager over a scratch users file, hashing with few rounds to keep tests fast
+    fn test_manager(name: &str) -> AuthManager {
+        AuthManager {
+            users: HashMap::new(),
+            users_file: std::env::temp_dir().join(format!("chess_users_{}_{}.json", name, std::process::id())),
+            kdf_iterations: 10,
+        }
+    }
+
+    #[test]
+    fn test_login_migration() {
+        let mut manager = test_manager("migration");
+
+        // An old unsalted hash is upgraded on the first successful login
+        let old_hash = format!("{:x}", md5_hash(b"secret"));
+        manager.users.insert("legacy".to_string(), legacy_user(&old_hash));
+        assert!(manager.login("legacy", "wrong").is_err());
+        assert!(manager.login("legacy", "secret").is_ok());
+        assert!(!manager.users["legacy"].needs_rehash(10));
+        assert!(manager.login("legacy", "secret").is_ok());
+
+        // A record without any hash can't be logged into or registered over
+        let mut old = legacy_user("");
+        old.username = "nohash".to_string();
+        old.rating = 1500;
+        manager.users.insert("nohash".to_string(), old);
+        assert!(manager.login("nohash", "guess").is_err());
+        assert!(manager.register("nohash".to_string(), "newpass".to_string()).is_err());
+        assert_eq!(manager.users["nohash"].rating, 1500);
+
+        // Setting a password keeps the statistics, and only works once
+        manager.reset_password("nohash", "newpass").unwrap();
+        let user = manager.login("nohash", "newpass").unwrap();
+        assert_eq!(user.rating, 1500);
+        assert!(manager.reset_password("nohash", "other").is_err());
+        assert!(manager.login("nohash", "other").is_err());
+        let _ = fs::remove_file(&manager.users_file);
+    }
+
+    #[test]
+    fn test_record_game() {
+        let mut user = legacy_user("");
+        user.record_game(GameResult::WhiteWins, WHITE, Some(("Computer (medium)", 1200)));
+        assert_eq!((user.games_played, user.games_won), (1, 1));
+        assert_eq!(user.rating, 1220);
+        assert_eq!(user.rating_history.len(), 1);
+
+        // Unrated games only count in the statistics
+        user.record_game(GameResult::Draw, BLACK, None);
+        assert_eq!((user.games_played, user.games_drawn, user.rating), (2, 1, 1220));
+        user.unrecord_game(GameResult::Draw, BLACK, false);
+
+        user.unrecord_game(GameResult::WhiteWins, WHITE, true);
+        assert_eq!((user.games_played, user.games_won, user.rating), (0, 0, 1200));
+        assert!(user.rating_history.is_empty());
+    }
+}
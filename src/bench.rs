@@ -0,0 +1,61 @@
+//! Fixed-depth search benchmark over a set of positions, for comparing search changes
+
+use crate::board::Board;
+use crate::search::*;
+use crate::utils::Timer;
+
+pub const DEFAULT_BENCH_DEPTH: u8 = 6;
+
+/// Opening, middlegame and endgame positions searched by `bench`
+const BENCH_POSITIONS: [&str; 12] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r2q1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QK2R w KQ - 0 9",
+    "2r2rk1/1bqnbppp/p2ppn2/1p6/3NP3/1BN1BP2/PPPQ2PP/2KR3R w - - 0 14",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+    "8/5pk1/6p1/8/4P3/5PK1/8/8 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+];
+
+/// Totals over all bench positions
+pub struct BenchResult {
+    pub nodes: u64,
+    pub time_ms: u128,
+}
+
+impl BenchResult {
+    pub fn nps(&self) -> u128 {
+        (self.nodes as u128 * 1000).checked_div(self.time_ms).unwrap_or(0)
+    }
+}
+
+/// Search every bench position to `depth` with a fresh 16 MB table, printing one
+/// line per position. The node total is deterministic, so it shows whether a
+/// change makes the search tree smaller.
+pub fn run_bench(depth: u8) -> BenchResult {
+    let timer = Timer::new();
+    let mut nodes = 0;
+
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let board = Board::from_fen(fen).expect("invalid bench FEN");
+        let mut searcher = Searcher::with_format(16, InfoFormat::Silent);
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        };
+
+        let best = searcher.search(&board, limits);
+        nodes += searcher.info.nodes;
+        println!("Position {:>2}: {:>10} nodes  {:<10} bestmove {}",
+                 i + 1, searcher.info.nodes, format_score(searcher.info.score), best.to_string());
+    }
+
+    let result = BenchResult { nodes, time_ms: timer.elapsed_ms() };
+    println!("Bench depth {}: {} nodes {} ms {} nps", depth, result.nodes, result.time_ms, result.nps());
+    result
+}
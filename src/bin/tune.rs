@@ -0,0 +1,328 @@
+//! Texel tuning: fit the evaluation weights to game results over a set of
+//! quiet positions, by minimising the squared error between each result and
+//! the win probability a sigmoid of the static evaluation predicts.
+//!
+//! Run with `cargo run --release --bin tune -- <dataset> [output.json] ...`
+
+use chess_engine::board::*;
+use chess_engine::eval::evaluate_with;
+use chess_engine::params::{active_params, EvalParams};
+use chess_engine::pawns::{clear_thread_pawn_table, same_cached_weights};
+use chess_engine::utils::Timer;
+use std::fs;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, Scope};
+
+/// Step each weight is first moved by; halved whenever a pass finds no improvement
+const INITIAL_STEP: i32 = 4;
+const DEFAULT_PASSES: usize = 100;
+const DEFAULT_OUTPUT: &str = "tuned_params.json";
+
+/// A position and the result of the game it came from, from white's point of view
+struct Sample {
+    board: Board,
+    result: f64,
+}
+
+/// Options for `tune <dataset> [output] [--init FILE] [--passes N] [--threads N]`
+struct TuneOptions {
+    dataset: String,
+    output: String,
+    /// Weights to start from instead of the active ones
+    init: Option<String>,
+    passes: usize,
+    threads: usize,
+}
+
+impl TuneOptions {
+    fn parse(args: &[String]) -> Result<TuneOptions, String> {
+        let usage = "Usage: tune <dataset> [output.json] [--init FILE] [--passes N] [--threads N]";
+        let mut options = TuneOptions {
+            dataset: String::new(),
+            output: DEFAULT_OUTPUT.to_string(),
+            init: None,
+            passes: DEFAULT_PASSES,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        let mut positional = Vec::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().cloned().ok_or_else(|| usage.to_string());
+            match arg.as_str() {
+                "--init" => options.init = Some(value()?),
+                "--passes" => options.passes = value()?.parse().map_err(|_| usage.to_string())?,
+                "--threads" => options.threads = value()?.parse::<usize>().map_err(|_| usage.to_string())?.max(1),
+                _ => positional.push(arg.clone()),
+            }
+        }
+
+        match positional.as_slice() {
+            [dataset] => options.dataset = dataset.clone(),
+            [dataset, output] => {
+                options.dataset = dataset.clone();
+                options.output = output.clone();
+            }
+            _ => return Err(usage.to_string()),
+        }
+        Ok(options)
+    }
+}
+
+/// Parse a game result written as `1-0`, `1/2-1/2`, `0-1`, `1.0`, `0.5` or `0.0`,
+/// optionally wrapped in brackets or quotes
+fn parse_result(token: &str) -> Option<f64> {
+    match token.trim_matches(|c| matches!(c, '[' | ']' | '"' | ';' | '(' | ')')) {
+        "1-0" | "1.0" | "1" => Some(1.0),
+        "1/2-1/2" | "0.5" => Some(0.5),
+        "0-1" | "0.0" | "0" => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Parse a dataset line: a FEN (the move counters may be left out, as in EPD)
+/// followed somewhere by the result, e.g. `<fen> [0.5]` or `<fen> c9 "1-0";`
+fn parse_line(line: &str) -> Option<Sample> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 5 {
+        return None;
+    }
+
+    let has_counters = tokens.len() > 6 && tokens[4].parse::<u16>().is_ok() && tokens[5].parse::<u16>().is_ok();
+    let (fen, rest) = if has_counters {
+        (tokens[..6].join(" "), &tokens[6..])
+    } else {
+        (format!("{} 0 1", tokens[..4].join(" ")), &tokens[4..])
+    };
+
+    let result = rest.iter().find_map(|token| parse_result(token))?;
+    let board = Board::from_fen(&fen).ok()?;
+    Some(Sample { board, result })
+}
+
+/// Load a dataset, one position per line. Blank lines and `#` comments are
+/// ignored; other lines that can't be parsed are counted and skipped.
+fn load_dataset(path: &str) -> Result<Vec<Sample>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut samples = Vec::new();
+    let mut skipped = 0;
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        match parse_line(line) {
+            Some(sample) => samples.push(sample),
+            None => skipped += 1,
+        }
+    }
+
+    if skipped > 0 {
+        println!("Skipped {} unreadable lines", skipped);
+    }
+    if samples.is_empty() {
+        return Err(format!("No positions in {}", path));
+    }
+    Ok(samples)
+}
+
+/// Expected score for white at a white-relative evaluation of `score` centipawns
+#[inline]
+fn sigmoid(score: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+fn squared_error_sum(samples: &[Sample], params: &EvalParams, k: f64) -> f64 {
+    samples
+        .iter()
+        .map(|sample| {
+            let score = evaluate_with(&sample.board, params);
+            let white_score = if sample.board.side == WHITE { score } else { -score };
+            (sample.result - sigmoid(white_score as f64, k)).powi(2)
+        })
+        .sum()
+}
+
+/// Worker threads that each own a share of the samples for the whole run, so
+/// their pawn tables stay warm from one error evaluation to the next
+struct ErrorPool {
+    jobs: Vec<Sender<(Arc<EvalParams>, f64)>>,
+    results: Receiver<(usize, f64)>,
+    samples: usize,
+}
+
+impl ErrorPool {
+    fn new<'scope>(scope: &'scope Scope<'scope, '_>, samples: &'scope [Sample], threads: usize) -> Self {
+        let (result_sender, results) = mpsc::channel();
+        let chunk = samples.len().div_ceil(threads.max(1));
+        let jobs = samples
+            .chunks(chunk)
+            .enumerate()
+            .map(|(worker, part)| {
+                let (job_sender, jobs) = mpsc::channel::<(Arc<EvalParams>, f64)>();
+                let result_sender = result_sender.clone();
+                scope.spawn(move || {
+                    let mut last: Option<Arc<EvalParams>> = None;
+                    for (params, k) in jobs {
+                        // Cached pawn scores only go stale when the pawn structure weights change
+                        if !last.is_some_and(|last| same_cached_weights(&last, &params)) {
+                            clear_thread_pawn_table();
+                        }
+                        let _ = result_sender.send((worker, squared_error_sum(part, &params, k)));
+                        last = Some(params);
+                    }
+                });
+                job_sender
+            })
+            .collect();
+        ErrorPool { jobs, results, samples: samples.len() }
+    }
+
+    /// Mean squared error of the predicted results. Partial sums are added in
+    /// worker order, so the result doesn't depend on which thread finishes first.
+    fn mean_squared_error(&self, params: &EvalParams, k: f64) -> f64 {
+        let params = Arc::new(params.clone());
+        for job in &self.jobs {
+            job.send((Arc::clone(&params), k)).expect("tuning worker stopped");
+        }
+        let mut sums = vec![0.0; self.jobs.len()];
+        for _ in 0..self.jobs.len() {
+            let (worker, sum) = self.results.recv().expect("tuning worker stopped");
+            sums[worker] = sum;
+        }
+        sums.iter().sum::<f64>() / self.samples as f64
+    }
+}
+
+/// Sigmoid scaling constant that best fits the current weights, by narrowing
+/// a scan around the best value found so far
+fn find_k(pool: &ErrorPool, params: &EvalParams) -> f64 {
+    let (mut best_k, mut step) = (1.0, 0.5);
+    let mut best_error = pool.mean_squared_error(params, best_k);
+
+    for _ in 0..6 {
+        let center = best_k;
+        for i in -4..=4 {
+            let k = center + i as f64 * step;
+            if k <= 0.0 {
+                continue;
+            }
+            let error = pool.mean_squared_error(params, k);
+            if error < best_error {
+                best_error = error;
+                best_k = k;
+            }
+        }
+        step /= 4.0;
+    }
+
+    best_k
+}
+
+/// Local search: nudge each weight up or down by a step and keep the change if
+/// the error drops. Writes the weights after every pass, so an interrupted run
+/// keeps its progress.
+fn run_tune(options: &TuneOptions) -> Result<EvalParams, String> {
+    let samples = load_dataset(&options.dataset)?;
+    let mut params = match &options.init {
+        Some(path) => EvalParams::load(path)?,
+        None => (*active_params()).clone(),
+    };
+    let timer = Timer::new();
+
+    // Each tunable weight as (group, index) into `EvalParams::values_mut`
+    let weights: Vec<(usize, usize)> = params
+        .values_mut()
+        .iter()
+        .enumerate()
+        .flat_map(|(group, values)| (0..values.len()).map(move |i| (group, i)))
+        .collect();
+
+    thread::scope(|scope| {
+        let pool = ErrorPool::new(scope, &samples, options.threads);
+        let k = find_k(&pool, &params);
+        let mut best_error = pool.mean_squared_error(&params, k);
+        println!("{} positions, {} weights, K = {:.4}, error {:.6}", samples.len(), weights.len(), k, best_error);
+
+        let mut step = INITIAL_STEP;
+        for pass in 1..=options.passes {
+            let mut changed = 0;
+            for &(group, i) in &weights {
+                for delta in [step, -step] {
+                    params.values_mut()[group][i] += delta;
+                    let error = pool.mean_squared_error(&params, k);
+                    if error < best_error {
+                        best_error = error;
+                        changed += 1;
+                        break;
+                    }
+                    params.values_mut()[group][i] -= delta;
+                }
+            }
+
+            params.save(&options.output)?;
+            println!("Pass {:>3}: step {} changed {:>4} error {:.6} ({:.0}s)",
+                     pass, step, changed, best_error, timer.elapsed_secs());
+
+            if changed == 0 {
+                if step == 1 {
+                    break;
+                }
+                step /= 2;
+            }
+        }
+        Ok::<(), String>(())
+    })?;
+
+    println!("Wrote {}", options.output);
+    Ok(params)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = TuneOptions::parse(&args).and_then(|options| run_tune(&options)) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess_engine::params::DEFAULT_PARAMS;
+
+    #[test]
+    fn test_parse_dataset_lines() {
+        let sample = parse_line("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 [0.5]").unwrap();
+        assert_eq!(sample.result, 0.5);
+        assert_eq!(sample.board.side, BLACK);
+
+        let sample = parse_line("4k3/8/8/8/8/8/8/3QK3 w - - c9 \"1-0\";").unwrap();
+        assert_eq!(sample.result, 1.0);
+        assert_eq!(sample.board.to_fen(), "4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+
+        assert_eq!(parse_line("4k3/8/8/8/8/8/8/3QK3 w - - 12 40 0-1").unwrap().result, 0.0);
+        assert!(parse_line("4k3/8/8/8/8/8/8/3QK3 w - -").is_none());
+        assert!(parse_line("not a position at all 1-0").is_none());
+    }
+
+    #[test]
+    fn test_error() {
+        assert_eq!(sigmoid(0.0, 1.0), 0.5);
+
+        let won = [parse_line("4k3/8/8/8/8/8/8/3QK3 b - - 0 1 1-0").unwrap()];
+        let lost = [parse_line("4k3/8/8/8/8/8/8/3QK3 b - - 0 1 0-1").unwrap()];
+        thread::scope(|scope| {
+            assert!(ErrorPool::new(scope, &won, 2).mean_squared_error(&DEFAULT_PARAMS, 1.0) < 0.01);
+            assert!(ErrorPool::new(scope, &lost, 2).mean_squared_error(&DEFAULT_PARAMS, 1.0) > 0.99);
+        });
+
+        // New pawn structure weights aren't hidden by scores in the workers' pawn tables
+        let doubled = [parse_line("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1 0.5").unwrap()];
+        thread::scope(|scope| {
+            let pool = ErrorPool::new(scope, &doubled, 1);
+            let before = pool.mean_squared_error(&DEFAULT_PARAMS, 1.0);
+            let params = EvalParams { doubled: (-1000, -1000), ..DEFAULT_PARAMS };
+            assert!(pool.mean_squared_error(&params, 1.0) > before + 0.1);
+            assert_eq!(pool.mean_squared_error(&DEFAULT_PARAMS, 1.0), before);
+        });
+    }
+}
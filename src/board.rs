@@ -1,7 +1,9 @@
 //! Board representation using bitboards and game state management.
 
+use crate::nnue::{Network, NnueState, PieceSquare};
 use crate::utils::*;
 use crate::zobrist::{zobrist, Zobrist};
+use std::sync::Arc;
 
 /// Piece types
 pub const PAWN: u8 = 0;
@@ -30,9 +32,11 @@ pub struct History {
     pub en_passant: Option<u8>,
     pub halfmove: u16,
     pub hash: u64,
+    pub pawn_hash: u64,
 }
 
 /// Chess board representation
+#[derive(Clone)]
 pub struct Board {
     /// Bitboards for each piece type [color][piece]
     pub pieces: [[u64; 6]; 2],
@@ -50,8 +54,12 @@ pub struct Board {
     pub fullmove: u16,
     /// Zobrist hash of current position
     pub hash: u64,
+    /// Zobrist hash of the pawns alone, for the pawn structure cache
+    pub pawn_hash: u64,
     /// Move history for unmake
     pub history: Vec<History>,
+    /// NNUE accumulators, kept up to date by make/unmake while a network is attached
+    pub nnue: Option<NnueState>,
 }
 
 impl Board {
@@ -66,7 +74,9 @@ impl Board {
             halfmove: 0,
             fullmove: 1,
             hash: 0,
+            pawn_hash: 0,
             history: Vec::new(),
+            nnue: None,
         }
     }
 
@@ -177,6 +187,7 @@ impl Board {
         
         // Calculate initial hash
         board.hash = board.calculate_hash();
+        board.pawn_hash = board.calculate_pawn_hash();
 
         Ok(board)
     }
@@ -317,6 +328,49 @@ impl Board {
         hash
     }
 
+    /// Calculate the pawn-only Zobrist hash from scratch
+    fn calculate_pawn_hash(&self) -> u64 {
+        let z = zobrist();
+        let mut hash = 0u64;
+
+        for color in 0..2 {
+            let mut bb = self.pieces[color][PAWN as usize];
+            while bb != 0 {
+                let sq = pop_lsb(&mut bb);
+                hash ^= z.pieces[Zobrist::piece_index(PAWN, color as u8)][sq as usize];
+            }
+        }
+
+        hash
+    }
+
+    /// Number of earlier occurrences of the current position. Only positions since
+    /// the last capture or pawn move (bounded by the halfmove clock) can repeat,
+    /// and none from before a null move, which no real game passes through.
+    pub fn repetition_count(&self) -> usize {
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove as usize)
+            .take_while(|h| h.mov != Move::NULL)
+            .skip(1)
+            .step_by(2)
+            .filter(|h| h.hash == self.hash)
+            .count()
+    }
+
+    /// Has the current position occurred before?
+    #[inline]
+    pub fn is_repetition(&self) -> bool {
+        self.repetition_count() > 0
+    }
+
+    /// Has the current position occurred at least three times?
+    #[inline]
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 2
+    }
+
     /// Make a move on the board
     pub fn make_move(&mut self, mov: Move) {
         let z = zobrist();
@@ -331,6 +385,7 @@ impl Board {
             en_passant: self.en_passant,
             halfmove: self.halfmove,
             hash: self.hash,
+            pawn_hash: self.pawn_hash,
         };
         self.history.push(hist);
 
@@ -357,6 +412,9 @@ impl Board {
             self.pieces[enemy as usize][captured_piece as usize] &= !bit_at(to);
             let cap_idx = Zobrist::piece_index(captured_piece, enemy);
             self.hash ^= z.pieces[cap_idx][to as usize];
+            if captured_piece == PAWN {
+                self.pawn_hash ^= z.pieces[cap_idx][to as usize];
+            }
             
             // Update castling rights if rook captured
             if captured_piece == ROOK {
@@ -380,6 +438,9 @@ impl Board {
         let piece_idx = Zobrist::piece_index(piece, color);
         self.pieces[color as usize][piece as usize] &= !bit_at(from);
         self.hash ^= z.pieces[piece_idx][from as usize];
+        if piece == PAWN {
+            self.pawn_hash ^= z.pieces[piece_idx][from as usize];
+        }
 
         // Handle special moves
         if mov.is_castle() {
@@ -410,10 +471,12 @@ impl Board {
             self.pieces[enemy as usize][PAWN as usize] &= !bit_at(captured_sq);
             let cap_idx = Zobrist::piece_index(PAWN, enemy);
             self.hash ^= z.pieces[cap_idx][captured_sq as usize];
+            self.pawn_hash ^= z.pieces[cap_idx][captured_sq as usize];
             
             // Place pawn
             self.pieces[color as usize][piece as usize] |= bit_at(to);
             self.hash ^= z.pieces[piece_idx][to as usize];
+            self.pawn_hash ^= z.pieces[piece_idx][to as usize];
         } else if mov.is_promotion() {
             // Promotion
             let promo_piece = mov.promotion();
@@ -424,6 +487,9 @@ impl Board {
             // Normal move
             self.pieces[color as usize][piece as usize] |= bit_at(to);
             self.hash ^= z.pieces[piece_idx][to as usize];
+            if piece == PAWN {
+                self.pawn_hash ^= z.pieces[piece_idx][to as usize];
+            }
             
             // Check for pawn double push
             if piece == PAWN && distance(from, to) == 2 {
@@ -480,6 +546,44 @@ impl Board {
 
         // Update occupancy
         self.update_occupancy();
+
+        if self.nnue.is_some() {
+            self.update_nnue(mov, piece, color, hist.captured);
+        }
+    }
+
+    /// Push NNUE accumulators for a move just made, from the pieces it moved
+    fn update_nnue(&mut self, mov: Move, piece: u8, color: u8, captured: Option<u8>) {
+        let (from, to) = (mov.from(), mov.to());
+        let enemy = color ^ 1;
+        let placed = if mov.is_promotion() { mov.promotion() } else { piece };
+        // At most two pieces leave and two arrive (castling)
+        let mut removed = [PieceSquare { piece, color, sq: from }; 2];
+        let mut added = [PieceSquare { piece: placed, color, sq: to }; 2];
+        let (mut removed_len, mut added_len) = (1, 1);
+
+        if let Some(captured) = captured {
+            removed[1] = PieceSquare { piece: captured, color: enemy, sq: to };
+            removed_len = 2;
+        } else if mov.is_en_passant() {
+            removed[1] = PieceSquare { piece: PAWN, color: enemy, sq: square(rank_of(from), file_of(to)) };
+            removed_len = 2;
+        } else if mov.is_castle() {
+            let (rook_from, rook_to) = if file_of(to) == 6 { (to + 1, to - 1) } else { (to - 2, to + 1) };
+            removed[1] = PieceSquare { piece: ROOK, color, sq: rook_from };
+            added[1] = PieceSquare { piece: ROOK, color, sq: rook_to };
+            (removed_len, added_len) = (2, 2);
+        }
+
+        if let Some(nnue) = &mut self.nnue {
+            nnue.push(&removed[..removed_len], &added[..added_len]);
+        }
+    }
+
+    /// Attach a network for NNUE evaluation, building fresh accumulators, or
+    /// detach it with `None`
+    pub fn set_nnue(&mut self, net: Option<Arc<Network>>) {
+        self.nnue = net.map(|net| NnueState::new(net, self));
     }
 
     /// Unmake the last move
@@ -598,9 +702,66 @@ impl Board {
         self.castling = hist.castling;
         self.en_passant = hist.en_passant;
         self.halfmove = hist.halfmove;
+        self.pawn_hash = hist.pawn_hash;
 
         // Update occupancy
         self.update_occupancy();
+
+        // Accumulators attached after this move was made have to be rebuilt
+        if let Some(mut nnue) = self.nnue.take() {
+            if !nnue.pop() {
+                nnue.refresh(self);
+            }
+            self.nnue = Some(nnue);
+        }
+    }
+
+    /// Pass the turn without moving (for null-move pruning). The halfmove clock
+    /// keeps counting, so the 50-move rule still applies below a null move.
+    pub fn make_null_move(&mut self) {
+        let z = zobrist();
+
+        self.history.push(History {
+            mov: Move::NULL,
+            captured: None,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove: self.halfmove,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
+        });
+
+        if let Some(ep) = self.en_passant.take() {
+            self.hash ^= z.en_passant[file_of(ep) as usize];
+        }
+        self.side ^= 1;
+        self.hash ^= z.side;
+        self.halfmove += 1;
+    }
+
+    /// Take back a null move made with `make_null_move`
+    pub fn unmake_null_move(&mut self) {
+        let hist = self.history.pop().expect("No null move to unmake");
+        debug_assert!(hist.mov == Move::NULL, "last move was not a null move");
+
+        self.side ^= 1;
+        self.en_passant = hist.en_passant;
+        self.halfmove = hist.halfmove;
+        self.hash = hist.hash;
+    }
+
+    /// Whether the last move played was a null move
+    #[inline]
+    pub fn last_move_was_null(&self) -> bool {
+        self.history.last().is_some_and(|h| h.mov == Move::NULL)
+    }
+
+    /// Whether `color` has any pieces besides pawns and the king. Without them
+    /// zugzwang is common, so passing is not a safe test of the position.
+    #[inline]
+    pub fn has_non_pawn_material(&self, color: u8) -> bool {
+        let pieces = &self.pieces[color as usize];
+        (pieces[KNIGHT as usize] | pieces[BISHOP as usize] | pieces[ROOK as usize] | pieces[QUEEN as usize]) != 0
     }
 }
 
@@ -609,3 +770,79 @@ impl Default for Board {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(board: &mut Board, moves: &[&str]) {
+        for m in moves {
+            let mov = crate::movegen::find_legal_move(board, Move::from_string(m).unwrap()).unwrap();
+            board.make_move(mov);
+        }
+    }
+
+    #[test]
+    fn test_repetition_count() {
+        let mut board = Board::starting_position();
+        play(&mut board, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(board.repetition_count(), 1);
+        assert!(!board.is_threefold_repetition());
+
+        play(&mut board, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert!(board.is_threefold_repetition());
+
+        // A pawn move resets the clock, so earlier positions no longer count
+        play(&mut board, &["e2e4"]);
+        assert_eq!(board.repetition_count(), 0);
+    }
+
+    #[test]
+    fn test_null_move() {
+        let mut board = Board::starting_position();
+        play(&mut board, &["e2e4"]);
+        let fen = board.to_fen();
+        let hash = board.hash;
+
+        board.make_null_move();
+        assert_eq!(board.side, WHITE);
+        assert_eq!(board.en_passant, None);
+        assert_eq!(board.hash, board.calculate_hash());
+        assert!(board.last_move_was_null());
+
+        board.unmake_null_move();
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.hash, hash);
+        assert!(!board.last_move_was_null());
+
+        // The halfmove clock runs on, but repetitions are not looked for past the null move
+        let mut board = Board::starting_position();
+        play(&mut board, &["g1f3", "g8f6", "f3g1"]);
+        board.make_null_move();
+        assert_eq!(board.halfmove, 4);
+        play(&mut board, &["g1f3", "f6g8"]);
+        assert_eq!(board.repetition_count(), 0);
+        play(&mut board, &["f3g1", "g8f6"]);
+        assert_eq!(board.repetition_count(), 1);
+
+        assert!(board.has_non_pawn_material(WHITE));
+        let pawns_only = Board::from_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1").unwrap();
+        assert!(!pawns_only.has_non_pawn_material(WHITE));
+    }
+
+    #[test]
+    fn test_pawn_hash() {
+        let mut board = Board::from_fen("r3k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let start = board.pawn_hash;
+
+        // En passant, a promotion capture and a king capturing a pawn
+        play(&mut board, &["e5d6", "e8d7", "b7a8q", "d7d6"]);
+        assert_eq!(board.pawn_hash, board.calculate_pawn_hash());
+        assert_eq!(board.pawn_hash, 0);
+
+        for _ in 0..4 {
+            board.unmake_move();
+        }
+        assert_eq!(board.pawn_hash, start);
+    }
+}
@@ -1,132 +1,105 @@
-//! Position evaluation using material and piece-square tables.
+//! Position evaluation using material, piece-square tables, pawn structure,
+//! king safety and piece activity.
 
 use crate::board::*;
+use crate::movegen::{bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks};
+use crate::params::*;
+use crate::pawns::{adjacent_files, evaluate_pawns, file_mask, pawn_attack_set, pawn_terms, ranks_ahead, relative_rank};
 use crate::utils::*;
+use serde::Serialize;
 
-/// Material values in centipawns
-const PIECE_VALUES: [i32; 6] = [
-    100,  // Pawn
-    320,  // Knight
-    330,  // Bishop
-    500,  // Rook
-    900,  // Queen
-    20000, // King
-];
-
-/// Piece-square tables for positional evaluation
-/// Values are from white's perspective, mirrored for black
-
-const PAWN_PST: [i32; 64] = [
-      0,   0,   0,   0,   0,   0,   0,   0,
-     50,  50,  50,  50,  50,  50,  50,  50,
-     10,  10,  20,  30,  30,  20,  10,  10,
-      5,   5,  10,  25,  25,  10,   5,   5,
-      0,   0,   0,  20,  20,   0,   0,   0,
-      5,  -5, -10,   0,   0, -10,  -5,   5,
-      5,  10,  10, -20, -20,  10,  10,   5,
-      0,   0,   0,   0,   0,   0,   0,   0,
-];
-
-const KNIGHT_PST: [i32; 64] = [
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    -40, -20,   0,   0,   0,   0, -20, -40,
-    -30,   0,  10,  15,  15,  10,   0, -30,
-    -30,   5,  15,  20,  20,  15,   5, -30,
-    -30,   0,  15,  20,  20,  15,   0, -30,
-    -30,   5,  10,  15,  15,  10,   5, -30,
-    -40, -20,   0,   5,   5,   0, -20, -40,
-    -50, -40, -30, -30, -30, -30, -40, -50,
-];
-
-const BISHOP_PST: [i32; 64] = [
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -10,   0,   5,  10,  10,   5,   0, -10,
-    -10,   5,   5,  10,  10,   5,   5, -10,
-    -10,   0,  10,  10,  10,  10,   0, -10,
-    -10,  10,  10,  10,  10,  10,  10, -10,
-    -10,   5,   0,   0,   0,   0,   5, -10,
-    -20, -10, -10, -10, -10, -10, -10, -20,
-];
-
-const ROOK_PST: [i32; 64] = [
-      0,   0,   0,   0,   0,   0,   0,   0,
-      5,  10,  10,  10,  10,  10,  10,   5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-     -5,   0,   0,   0,   0,   0,   0,  -5,
-      0,   0,   0,   5,   5,   0,   0,   0,
-];
-
-const QUEEN_PST: [i32; 64] = [
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -10,   0,   5,   5,   5,   5,   0, -10,
-     -5,   0,   5,   5,   5,   5,   0,  -5,
-      0,   0,   5,   5,   5,   5,   0,  -5,
-    -10,   5,   5,   5,   5,   5,   0, -10,
-    -10,   0,   5,   0,   0,   0,   0, -10,
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-];
-
-const KING_PST_MG: [i32; 64] = [
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -20, -30, -30, -40, -40, -30, -30, -20,
-    -10, -20, -20, -20, -20, -20, -20, -10,
-     20,  20,   0,   0,   0,   0,  20,  20,
-     20,  30,  10,   0,   0,  10,  30,  20,
-];
-
-/// Get piece-square table for a piece
-const fn get_pst(piece: u8) -> &'static [i32; 64] {
+/// Contribution of each piece type to the game phase
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+/// Phase with all pieces on the board; 0 is a bare pawn ending
+pub const MAX_PHASE: i32 = 24;
+
+/// Middlegame and endgame piece-square tables for a piece
+const fn get_pst(params: &EvalParams, piece: u8) -> (&[i32; 64], &[i32; 64]) {
     match piece {
-        PAWN => &PAWN_PST,
-        KNIGHT => &KNIGHT_PST,
-        BISHOP => &BISHOP_PST,
-        ROOK => &ROOK_PST,
-        QUEEN => &QUEEN_PST,
-        KING => &KING_PST_MG,
-        _ => &[0; 64],
+        PAWN => (&params.pawn_mg, &params.pawn_eg),
+        KNIGHT => (&params.knight_mg, &params.knight_eg),
+        BISHOP => (&params.bishop_mg, &params.bishop_eg),
+        ROOK => (&params.rook_mg, &params.rook_eg),
+        QUEEN => (&params.queen_mg, &params.queen_eg),
+        _ => (&params.king_mg, &params.king_eg),
     }
 }
 
-/// Mirror square for black pieces
+/// Table index for a square. The tables list rank 8 first, so white's squares
+/// are flipped vertically and black's, seen from black's side, are used as is.
 #[inline]
-const fn mirror(sq: u8) -> usize {
-    (sq ^ 56) as usize
+const fn pst_index(sq: u8, color: u8) -> usize {
+    if color == WHITE {
+        (sq ^ 56) as usize
+    } else {
+        sq as usize
+    }
 }
 
-/// Evaluate position from side to move perspective
-pub fn evaluate(board: &Board) -> i32 {
-    let mut score = 0;
+/// Game phase from the remaining pieces: `MAX_PHASE` in the opening, 0 with
+/// only kings and pawns left
+pub fn game_phase(board: &Board) -> i32 {
+    let mut phase = 0;
+    for piece in KNIGHT..=QUEEN {
+        let count = popcount(board.pieces[WHITE as usize][piece as usize])
+            + popcount(board.pieces[BLACK as usize][piece as usize]);
+        phase += count as i32 * PHASE_WEIGHTS[piece as usize];
+    }
+    phase.min(MAX_PHASE)
+}
+
+/// Blend middlegame and endgame scores by game phase
+#[inline]
+pub fn taper(mg: i32, eg: i32, phase: i32) -> i32 {
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+/// Evaluate position from side to move perspective, with the attached NNUE
+/// network if there is one and the classical evaluation otherwise
+pub fn evaluate(board: &Board, params: &EvalParams) -> i32 {
+    if let Some(nnue) = &board.nnue {
+        return nnue.evaluate(board.side);
+    }
+    evaluate_with(board, params)
+}
+
+/// Classical evaluation with the given weights, from side to move perspective
+pub fn evaluate_with(board: &Board, params: &EvalParams) -> i32 {
+    let mut mg = 0;
+    let mut eg = 0;
     
     // Material and piece-square tables
-    for piece in 0..6 {
-        // White pieces
-        let mut white_pieces = board.pieces[WHITE as usize][piece as usize];
-        while white_pieces != 0 {
-            let sq = pop_lsb(&mut white_pieces);
-            score += PIECE_VALUES[piece as usize];
-            score += get_pst(piece)[sq as usize];
-        }
-        
-        // Black pieces
-        let mut black_pieces = board.pieces[BLACK as usize][piece as usize];
-        while black_pieces != 0 {
-            let sq = pop_lsb(&mut black_pieces);
-            score -= PIECE_VALUES[piece as usize];
-            score -= get_pst(piece)[mirror(sq)];
+    for color in [WHITE, BLACK] {
+        let sign = if color == WHITE { 1 } else { -1 };
+        for piece in 0..6 {
+            let (mg_table, eg_table) = get_pst(params, piece);
+            let mut pieces = board.pieces[color as usize][piece as usize];
+            while pieces != 0 {
+                let idx = pst_index(pop_lsb(&mut pieces), color);
+                mg += sign * (params.mg_piece_values[piece as usize] + mg_table[idx]);
+                eg += sign * (params.eg_piece_values[piece as usize] + eg_table[idx]);
+            }
         }
     }
     
-    // Mobility bonus (simple)
-    let mobility_bonus = calculate_mobility(board);
-    score += mobility_bonus;
+    // Pawn structure
+    let (pawn_mg, pawn_eg) = evaluate_pawns(board, params);
+    mg += pawn_mg;
+    eg += pawn_eg;
+    
+    // King safety only matters while there are pieces left to attack with
+    mg += king_safety(board, WHITE, params) - king_safety(board, BLACK, params);
+    
+    // Mobility and piece placement
+    for color in [WHITE, BLACK] {
+        let sign = if color == WHITE { 1 } else { -1 };
+        let (mobility_mg, mobility_eg) = mobility(board, color, params);
+        let (pieces_mg, pieces_eg) = piece_terms(board, color, params);
+        mg += sign * (mobility_mg + pieces_mg);
+        eg += sign * (mobility_eg + pieces_eg);
+    }
+    
+    let score = taper(mg, eg, game_phase(board));
     
     // Return score from side to move perspective
     if board.side == WHITE {
@@ -136,41 +109,321 @@ pub fn evaluate(board: &Board) -> i32 {
     }
 }
 
-/// Calculate simple mobility bonus
-fn calculate_mobility(board: &Board) -> i32 {
-    let white_mobility = count_mobility(board, WHITE);
-    let black_mobility = count_mobility(board, BLACK);
-    (white_mobility - black_mobility) * 5
+/// Middlegame and endgame halves of a score
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+
+    const fn minus(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+/// One evaluation term: each side's share from its own point of view, their
+/// difference from white's, and that difference tapered by the game phase
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceTerm {
+    pub name: &'static str,
+    pub white: Score,
+    pub black: Score,
+    pub total: Score,
+    pub tapered: i32,
+}
+
+/// Term-by-term breakdown of the classical evaluation, for `eval trace`
+#[derive(Clone, Debug, Serialize)]
+pub struct EvalTrace {
+    pub terms: Vec<TraceTerm>,
+    pub phase: i32,
+    pub max_phase: i32,
+    /// Sum of the terms before tapering
+    pub total: Score,
+    /// Classical score from white's point of view. Tapering the sum rather than
+    /// each term, so it may differ from the sum of the tapered terms by rounding.
+    pub score: i32,
+    /// Classical score from the side to move's point of view, as `evaluate_with` returns it
+    pub side_to_move_score: i32,
+    /// Score of the attached NNUE network from white's point of view, which
+    /// the search uses instead of the classical one
+    pub nnue: Option<i32>,
 }
 
-/// Count mobility for a side (number of squares attacked)
-fn count_mobility(board: &Board, color: u8) -> i32 {
+const PST_TERM_NAMES: [&str; 6] = ["pst pawn", "pst knight", "pst bishop", "pst rook", "pst queen", "pst king"];
+
+/// A term evaluated for white and black
+fn per_side(term: impl Fn(u8) -> (i32, i32)) -> [Score; 2] {
+    [WHITE, BLACK].map(|color| {
+        let (mg, eg) = term(color);
+        Score::new(mg, eg)
+    })
+}
+
+/// Evaluate term by term. The terms add up to what `evaluate_with` returns.
+pub fn trace(board: &Board, params: &EvalParams) -> EvalTrace {
+    let phase = game_phase(board);
+    let mut sides: Vec<(&'static str, [Score; 2])> = Vec::new();
+
+    let mut material = [Score::default(); 2];
+    let mut pst = [[Score::default(); 2]; 6];
+    for color in [WHITE, BLACK] {
+        let c = color as usize;
+        for piece in 0..6 {
+            let (mg_table, eg_table) = get_pst(params, piece);
+            let p = piece as usize;
+            let mut pieces = board.pieces[c][p];
+            while pieces != 0 {
+                let idx = pst_index(pop_lsb(&mut pieces), color);
+                material[c].mg += params.mg_piece_values[p];
+                material[c].eg += params.eg_piece_values[p];
+                pst[p][c].mg += mg_table[idx];
+                pst[p][c].eg += eg_table[idx];
+            }
+        }
+    }
+    sides.push(("material", material));
+    for piece in 0..6 {
+        sides.push((PST_TERM_NAMES[piece], pst[piece]));
+    }
+
+    sides.push(("pawns", per_side(|color| pawn_terms(board, color, params))));
+    sides.push(("king safety", per_side(|color| (king_safety(board, color, params), 0))));
+    sides.push(("mobility", per_side(|color| mobility(board, color, params))));
+    sides.push(("pieces", per_side(|color| piece_terms(board, color, params))));
+
+    let mut total = Score::default();
+    let terms = sides
+        .into_iter()
+        .map(|(name, [white, black])| {
+            let diff = white.minus(black);
+            total = Score::new(total.mg + diff.mg, total.eg + diff.eg);
+            TraceTerm { name, white, black, total: diff, tapered: taper(diff.mg, diff.eg, phase) }
+        })
+        .collect();
+
+    let score = taper(total.mg, total.eg, phase);
+    EvalTrace {
+        terms,
+        phase,
+        max_phase: MAX_PHASE,
+        total,
+        score,
+        side_to_move_score: if board.side == WHITE { score } else { -score },
+        nnue: board.nnue.as_ref().map(|nnue| {
+            let score = nnue.evaluate(board.side);
+            if board.side == WHITE { score } else { -score }
+        }),
+    }
+}
+
+impl EvalTrace {
+    /// Trace as a table, one row per term
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let rule = format!("{}\n", "-".repeat(69));
+        out.push_str(&format!("{:<12} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6} | {:>7}\n",
+                              "Term", "White", "", "Black", "", "Total", "", "Tapered"));
+        out.push_str(&format!("{:<12} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6} |\n",
+                              "", "mg", "eg", "mg", "eg", "mg", "eg"));
+        out.push_str(&rule);
+        for term in &self.terms {
+            out.push_str(&format!("{:<12} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6} | {:>7}\n",
+                                  term.name, term.white.mg, term.white.eg, term.black.mg, term.black.eg,
+                                  term.total.mg, term.total.eg, term.tapered));
+        }
+        out.push_str(&rule);
+        out.push_str(&format!("{:<12} | {:>13} | {:>13} | {:>6} {:>6} | {:>7}\n",
+                              "Total", "", "", self.total.mg, self.total.eg, self.score));
+        out.push_str(&format!("\nPhase {}/{} ({}% middlegame, {}% endgame)\n", self.phase, self.max_phase,
+                              self.phase * 100 / self.max_phase, 100 - self.phase * 100 / self.max_phase));
+        out.push_str(&format!("Classical: {} (white), {} (side to move)\n", self.score, self.side_to_move_score));
+        if let Some(nnue) = self.nnue {
+            out.push_str(&format!("NNUE:      {} (white), used by the search\n", nnue));
+        }
+        out
+    }
+}
+
+/// Middlegame king safety for `color`: the pawn shield in front of the king,
+/// enemy pawns storming it, open files next to it, and the enemy pieces
+/// attacking the squares around it
+pub fn king_safety(board: &Board, color: u8, params: &EvalParams) -> i32 {
+    let enemy = color ^ 1;
+    let king_sq = lsb(board.pieces[color as usize][KING as usize]);
+    if king_sq >= 64 {
+        return 0;
+    }
+    let ours = board.pieces[color as usize][PAWN as usize];
+    let theirs = board.pieces[enemy as usize][PAWN as usize];
+    let ahead = ranks_ahead(rank_of(king_sq), color);
+    let king_rank = relative_rank(king_sq, color);
+    let mut score = 0;
+
+    let king_file = file_of(king_sq);
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+        let mask = file_mask(file);
+
+        let shield = closest_pawn(ours & mask & ahead, color);
+        score += params.pawn_shield[shield.map_or(0, |sq| relative_rank(sq, color) - king_rank)];
+
+        let storm = closest_pawn(theirs & mask & ahead, color);
+        score += params.pawn_storm[storm.map_or(0, |sq| relative_rank(sq, color) - king_rank)];
+
+        if ours & mask == 0 {
+            score += if theirs & mask == 0 { params.open_king_file } else { params.semi_open_king_file };
+        }
+    }
+
+    // Attack units from enemy pieces hitting the king zone. A single attacker
+    // is rarely dangerous, so the penalty needs at least two.
+    let zone = king_attacks(king_sq) | bit_at(king_sq);
     let occupied = board.all_occupancy();
-    let our_pieces = board.occupancy[color as usize];
-    let mut mobility = 0;
-    
-    // Knight mobility
-    let mut knights = board.pieces[color as usize][KNIGHT as usize];
-    while knights != 0 {
-        let sq = pop_lsb(&mut knights);
-        mobility += popcount(crate::movegen::knight_attacks(sq) & !our_pieces) as i32;
+    let mut attackers = 0;
+    let mut units = 0;
+    for piece in KNIGHT..=QUEEN {
+        let mut pieces = board.pieces[enemy as usize][piece as usize];
+        while pieces != 0 {
+            let sq = pop_lsb(&mut pieces);
+            let attacks = piece_attacks(piece, sq, occupied) & zone;
+            if attacks != 0 {
+                attackers += 1;
+                units += params.attack_weights[piece as usize] * popcount(attacks) as i32;
+            }
+        }
     }
-    
-    // Bishop mobility
+    if attackers >= 2 {
+        score -= params.king_danger[(units as usize).min(params.king_danger.len() - 1)];
+    }
+
+    score
+}
+
+/// The pawn in `pawns` closest to `color`'s back rank
+#[inline]
+fn closest_pawn(pawns: u64, color: u8) -> Option<u8> {
+    match (pawns, color) {
+        (0, _) => None,
+        (_, WHITE) => Some(lsb(pawns)),
+        _ => Some(msb(pawns)),
+    }
+}
+
+/// Mobility of `color`'s knights, bishops, rooks and queens as (middlegame, endgame)
+pub fn mobility(board: &Board, color: u8, params: &EvalParams) -> (i32, i32) {
+    let occupied = board.all_occupancy();
+    let area = !board.occupancy[color as usize]
+        & !pawn_attack_set(board.pieces[(color ^ 1) as usize][PAWN as usize], color ^ 1);
+    let (mut mg, mut eg) = (0, 0);
+
+    for piece in KNIGHT..=QUEEN {
+        let (mg_table, eg_table): (&[i32], &[i32]) = match piece {
+            KNIGHT => (&params.knight_mobility_mg, &params.knight_mobility_eg),
+            BISHOP => (&params.bishop_mobility_mg, &params.bishop_mobility_eg),
+            ROOK => (&params.rook_mobility_mg, &params.rook_mobility_eg),
+            _ => (&params.queen_mobility_mg, &params.queen_mobility_eg),
+        };
+        let mut pieces = board.pieces[color as usize][piece as usize];
+        while pieces != 0 {
+            let sq = pop_lsb(&mut pieces);
+            let count = popcount(piece_attacks(piece, sq, occupied) & area) as usize;
+            mg += mg_table[count];
+            eg += eg_table[count];
+        }
+    }
+
+    (mg, eg)
+}
+
+/// Squares attacked by a knight, bishop, rook or queen
+#[inline]
+fn piece_attacks(piece: u8, sq: u8, occupied: u64) -> u64 {
+    match piece {
+        KNIGHT => knight_attacks(sq),
+        BISHOP => bishop_attacks(sq, occupied),
+        ROOK => rook_attacks(sq, occupied),
+        _ => queen_attacks(sq, occupied),
+    }
+}
+
+/// Bishop pair, rook files and seventh rank, outposts and trapped pieces for
+/// `color` as (middlegame, endgame)
+pub fn piece_terms(board: &Board, color: u8, params: &EvalParams) -> (i32, i32) {
+    let enemy = color ^ 1;
+    let ours = board.pieces[color as usize][PAWN as usize];
+    let theirs = board.pieces[enemy as usize][PAWN as usize];
+    let occupied = board.all_occupancy();
+    let king_sq = lsb(board.pieces[color as usize][KING as usize]);
+    let enemy_king = lsb(board.pieces[enemy as usize][KING as usize]);
+    let mut total = (0, 0);
+    let mut add = |(mg, eg): (i32, i32)| {
+        total.0 += mg;
+        total.1 += eg;
+    };
+
+    if popcount(board.pieces[color as usize][BISHOP as usize]) >= 2 {
+        add(params.bishop_pair);
+    }
+
+    for piece in [KNIGHT, BISHOP] {
+        let bonus = if piece == KNIGHT { params.knight_outpost } else { params.bishop_outpost };
+        let mut pieces = board.pieces[color as usize][piece as usize];
+        while pieces != 0 {
+            let sq = pop_lsb(&mut pieces);
+            let safe = theirs & adjacent_files(file_of(sq)) & ranks_ahead(rank_of(sq), color) == 0;
+            let defended = pawn_attacks(sq, enemy) & ours != 0;
+            if (3..=5).contains(&relative_rank(sq, color)) && safe && defended {
+                add(bonus);
+            }
+        }
+    }
+
+    // The bishop is on a7/h7 from its own side, and the pawn shutting it in on b6/g6
+    let flip = if color == WHITE { 0 } else { 56 };
     let mut bishops = board.pieces[color as usize][BISHOP as usize];
     while bishops != 0 {
         let sq = pop_lsb(&mut bishops);
-        mobility += popcount(crate::movegen::bishop_attacks(sq, occupied) & !our_pieces) as i32;
+        let trapped = match sq ^ flip {
+            48 => is_set(theirs, 41 ^ flip),
+            55 => is_set(theirs, 46 ^ flip),
+            _ => false,
+        };
+        if trapped {
+            add(params.trapped_bishop);
+        }
     }
-    
-    // Rook mobility
+
+    let seventh_rank = if color == WHITE { 0xFFu64 << 48 } else { 0xFFu64 << 8 };
     let mut rooks = board.pieces[color as usize][ROOK as usize];
     while rooks != 0 {
         let sq = pop_lsb(&mut rooks);
-        mobility += popcount(crate::movegen::rook_attacks(sq, occupied) & !our_pieces) as i32;
+        let file = file_mask(file_of(sq));
+        if ours & file == 0 {
+            add(if theirs & file == 0 { params.rook_open_file } else { params.rook_semi_open_file });
+        }
+
+        if relative_rank(sq, color) == 6
+            && (theirs & seventh_rank != 0 || (enemy_king < 64 && relative_rank(enemy_king, color) == 7))
+        {
+            add(params.rook_on_seventh);
+        }
+
+        // A king that has left the e-file can't castle any more
+        if king_sq < 64 && relative_rank(sq, color) == 0 && relative_rank(king_sq, color) == 0 {
+            let (rook_file, king_file) = (file_of(sq), file_of(king_sq));
+            let cornered = (king_file > 4 && rook_file > king_file) || (king_file < 4 && rook_file < king_file);
+            if cornered && popcount(rook_attacks(sq, occupied) & !board.occupancy[color as usize]) <= 3 {
+                add(params.trapped_rook);
+            }
+        }
     }
-    
-    mobility
+
+    total
 }
 
 /// Check if position is likely drawn by insufficient material
@@ -188,6 +441,127 @@ pub fn is_insufficient_material(board: &Board) -> bool {
             return true;
         }
     }
+
+    // Only bishops left, all on squares of one colour: neither side can mate
+    const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+    let bishops = board.pieces[0][BISHOP as usize] | board.pieces[1][BISHOP as usize];
+    let kings = board.pieces[0][KING as usize] | board.pieces[1][KING as usize];
+    if board.all_occupancy() == bishops | kings
+        && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    {
+        return true;
+    }
     
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_of(fen: &str) -> i32 {
+        evaluate(&Board::from_fen(fen).unwrap(), &DEFAULT_PARAMS)
+    }
+
+    #[test]
+    fn test_symmetry() {
+        assert_eq!(eval_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 0);
+        // Mirrored positions score the same for the side to move
+        assert_eq!(
+            eval_of("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"),
+            eval_of("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4")
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bq1rk1/pp3ppp/2n1pn2/3p4/1bPP4/2N1PN2/PP1B1PPP/R2QKB1R b KQ - 3 8",
+            "8/5pk1/6p1/3P4/8/5PK1/1r6/3R4 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let trace = trace(&board, &DEFAULT_PARAMS);
+            assert_eq!(trace.side_to_move_score, evaluate_with(&board, &DEFAULT_PARAMS));
+            assert_eq!(trace.phase, game_phase(&board));
+            assert_eq!(trace.total.mg, trace.terms.iter().map(|t| t.total.mg).sum::<i32>());
+            assert!(trace.nnue.is_none());
+        }
+
+        let trace = trace(&Board::starting_position(), &DEFAULT_PARAMS);
+        let material = &trace.terms[0];
+        assert_eq!(material.name, "material");
+        assert_eq!(material.white, material.black);
+        assert_eq!(material.total, Score::default());
+        assert!(trace.to_text().contains("pst knight"));
+    }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(game_phase(&Board::starting_position()), MAX_PHASE);
+        assert_eq!(game_phase(&Board::from_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1").unwrap()), 0);
+        assert_eq!(game_phase(&Board::from_fen("3rk3/8/8/8/8/8/8/2N1K3 w - - 0 1").unwrap()), 3);
+        assert_eq!(taper(100, 20, MAX_PHASE), 100);
+        assert_eq!(taper(100, 20, 0), 20);
+    }
+
+    #[test]
+    fn test_king_placement() {
+        // Castled king is safer in the middlegame
+        assert!(eval_of("r1bq1rk1/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQ1RK1 w - - 0 1")
+            > eval_of("r1bq1rk1/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPPKPPP/R1BQ1R2 w - - 0 1"));
+        // but belongs in the centre in a pawn ending
+        assert!(eval_of("8/5k2/8/8/4K3/8/4P3/8 w - - 0 1") > eval_of("8/5k2/8/8/8/8/4P3/K7 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_king_safety() {
+        let safety = |fen: &str| king_safety(&Board::from_fen(fen).unwrap(), WHITE, &DEFAULT_PARAMS);
+
+        // An intact shield beats pushed and missing pawns
+        let intact = safety("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1");
+        assert!(intact > safety("6k1/8/8/8/8/6PP/5P2/6K1 w - - 0 1"));
+        assert!(intact > safety("6k1/8/8/8/8/8/5P1P/6K1 w - - 0 1"));
+
+        // Enemy pawns storming the king
+        assert!(intact > safety("6k1/8/8/8/8/6p1/5PPP/6K1 w - - 0 1"));
+        assert!(intact > safety("6k1/8/8/8/7p/8/5PPP/6K1 w - - 0 1"));
+
+        // A queen alone is tolerated, a queen and a knight together are not
+        let queen = safety("6k1/8/8/8/8/5q2/5PPP/6K1 w - - 0 1");
+        let queen_knight = safety("6k1/8/8/8/7n/5q2/5PPP/6K1 w - - 0 1");
+        assert_eq!(queen, intact);
+        assert!(queen_knight < intact - 20);
+    }
+
+    #[test]
+    fn test_piece_terms() {
+        let terms = |fen: &str, color: u8| piece_terms(&Board::from_fen(fen).unwrap(), color, &DEFAULT_PARAMS);
+
+        assert_eq!(terms("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", WHITE), DEFAULT_PARAMS.bishop_pair);
+        assert_eq!(terms("4k3/1p6/8/8/8/8/8/R3K3 w - - 0 1", WHITE), DEFAULT_PARAMS.rook_open_file);
+        assert_eq!(terms("4k3/p7/8/8/8/8/8/R3K3 w - - 0 1", WHITE), DEFAULT_PARAMS.rook_semi_open_file);
+        assert_eq!(terms("4k3/R7/8/8/8/8/8/4K3 w - - 0 1", WHITE),
+                   (DEFAULT_PARAMS.rook_open_file.0 + DEFAULT_PARAMS.rook_on_seventh.0, DEFAULT_PARAMS.rook_open_file.1 + DEFAULT_PARAMS.rook_on_seventh.1));
+
+        // An outpost needs a defending pawn and no enemy pawn able to chase the piece away
+        assert_eq!(terms("4k3/8/8/4N3/3P4/8/8/4K3 w - - 0 1", WHITE), DEFAULT_PARAMS.knight_outpost);
+        assert_eq!(terms("4k3/5p2/8/4N3/3P4/8/8/4K3 w - - 0 1", WHITE), (0, 0));
+        assert_eq!(terms("4k3/8/8/3p4/4n3/8/8/4K3 b - - 0 1", BLACK), DEFAULT_PARAMS.knight_outpost);
+
+        assert_eq!(terms("4k3/8/8/8/8/8/5PPP/5KR1 w - - 0 1", WHITE), DEFAULT_PARAMS.trapped_rook);
+        assert_eq!(terms("4k3/B7/1p6/8/8/8/8/4K3 w - - 0 1", WHITE), DEFAULT_PARAMS.trapped_bishop);
+        assert_eq!(terms("4k3/8/8/8/8/1P6/b7/4K3 b - - 0 1", BLACK), DEFAULT_PARAMS.trapped_bishop);
+    }
+
+    #[test]
+    fn test_mobility() {
+        let board = Board::starting_position();
+        assert_eq!(mobility(&board, WHITE, &DEFAULT_PARAMS), mobility(&board, BLACK, &DEFAULT_PARAMS));
+
+        // Queens count, and squares covered by enemy pawns don't
+        let centre = Board::from_fen("4k3/8/8/8/3Q4/8/8/4K3 w - - 0 1").unwrap();
+        let covered = Board::from_fen("4k3/8/2p1p3/8/3Q4/8/8/4K3 w - - 0 1").unwrap();
+        assert!(mobility(&centre, WHITE, &DEFAULT_PARAMS).0 > mobility(&covered, WHITE, &DEFAULT_PARAMS).0);
+    }
+}
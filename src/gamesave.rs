@@ -1,6 +1,8 @@
 //! Game save and load functionality
 
 use crate::board::Board;
+use crate::gamestatus::GameOutcome;
+use crate::movegen::find_legal_move;
 use crate::utils::Move;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -11,28 +13,62 @@ const SAVE_DIR: &str = ".chess_saves";
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedGame {
     pub username: String,
+    /// Position the game started from; empty in saves made before it was recorded
+    #[serde(default)]
+    pub start_fen: String,
+    /// Position when the game was saved
     pub fen: String,
     pub moves: Vec<String>,
     pub timestamp: String,
     pub white_player: String,
     pub black_player: String,
+    /// How the game ended, or None if it was saved in progress
+    #[serde(default)]
+    pub outcome: Option<GameOutcome>,
 }
 
 impl SavedGame {
-    pub fn new(username: String, board: &Board, moves: &[Move], white: String, black: String) -> Self {
+    pub fn new(username: String, start: &Board, board: &Board, moves: &[Move], white: String,
+               black: String, outcome: Option<GameOutcome>) -> Self {
         SavedGame {
             username,
+            start_fen: start.to_fen(),
             fen: board.to_fen(),
             moves: moves.iter().map(|m| m.to_string()).collect(),
             timestamp: chrono_timestamp(),
             white_player: white,
             black_player: black,
+            outcome,
         }
     }
 }
 
+impl SavedGame {
+    /// Rebuild the board, with its full history, by replaying the saved moves
+    /// from the starting position. Older saves without a starting position only
+    /// restore the final position.
+    pub fn replay(&self) -> Result<Board, String> {
+        if self.start_fen.is_empty() {
+            return Board::from_fen(&self.fen);
+        }
+
+        let mut board = Board::from_fen(&self.start_fen)?;
+        for (i, move_str) in self.moves.iter().enumerate() {
+            let mov = Move::from_string(move_str)
+                .and_then(|m| find_legal_move(&mut board, m))
+                .ok_or_else(|| format!("Corrupt save: illegal move '{}' at ply {}", move_str, i + 1))?;
+            board.make_move(mov);
+        }
+
+        if board.to_fen() != self.fen {
+            return Err("Corrupt save: moves do not lead to the saved position".to_string());
+        }
+        Ok(board)
+    }
+}
+
 // Simple timestamp function
-fn chrono_timestamp() -> String {
+pub fn chrono_timestamp() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
     let duration = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -113,3 +149,58 @@ impl Default for GameManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(start_fen: &str, fen: &str, moves: &[&str]) -> SavedGame {
+        SavedGame {
+            username: "test".to_string(),
+            start_fen: start_fen.to_string(),
+            fen: fen.to_string(),
+            moves: moves.iter().map(|m| m.to_string()).collect(),
+            timestamp: "0".to_string(),
+            white_player: "White".to_string(),
+            black_player: "Black".to_string(),
+            outcome: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_restores_history() {
+        let mut board = Board::starting_position();
+        let start = board.clone();
+        let mut moves = Vec::new();
+        for m in ["e2e4", "e7e5", "g1f3"] {
+            let mov = find_legal_move(&mut board, Move::from_string(m).unwrap()).unwrap();
+            board.make_move(mov);
+            moves.push(mov);
+        }
+
+        let game = SavedGame::new("test".to_string(), &start, &board, &moves,
+                                  "White".to_string(), "Black".to_string(), None);
+        let restored = game.replay().unwrap();
+        assert_eq!(restored.to_fen(), board.to_fen());
+        assert_eq!(restored.history.len(), 3);
+        assert_eq!(restored.hash, board.hash);
+    }
+
+    #[test]
+    fn test_replay_rejects_bad_saves() {
+        let start = Board::starting_position().to_fen();
+
+        let game = saved(&start, &start, &["e2e4", "e7e5", "e4e5"]);
+        match game.replay() {
+            Err(e) => assert!(e.contains("'e4e5' at ply 3"), "{}", e),
+            Ok(_) => panic!("illegal move accepted"),
+        }
+
+        let game = saved(&start, &start, &["e2e4"]);
+        assert!(game.replay().is_err());
+
+        // Legacy saves without a starting position load the final position only
+        let game = saved("", &start, &["e2e4"]);
+        assert_eq!(game.replay().unwrap().history.len(), 0);
+    }
+}
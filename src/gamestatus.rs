@@ -0,0 +1,174 @@
+//! Game termination rules: checkmate, stalemate, move-count rules, repetition and insufficient material
+
+use crate::board::*;
+use crate::eval::is_insufficient_material;
+use crate::movegen::*;
+use serde::{Deserialize, Serialize};
+
+/// Final score of a game
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    /// PGN result token
+    pub const fn as_pgn(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+
+    /// Score for the given side: 1.0 win, 0.5 draw, 0.0 loss
+    pub fn score_for(self, color: u8) -> f64 {
+        match (self, color) {
+            (GameResult::Draw, _) => 0.5,
+            (GameResult::WhiteWins, WHITE) | (GameResult::BlackWins, BLACK) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Why a game ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    /// 50 moves without capture or pawn move (must be claimed)
+    FiftyMoveRule,
+    /// 75 moves without capture or pawn move (automatic)
+    SeventyFiveMoveRule,
+    /// Position occurred three times (must be claimed)
+    ThreefoldRepetition,
+    /// Position occurred five times (automatic)
+    FivefoldRepetition,
+}
+
+impl Termination {
+    pub const fn description(self) -> &'static str {
+        match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::FiftyMoveRule => "50-move rule",
+            Termination::SeventyFiveMoveRule => "75-move rule",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FivefoldRepetition => "fivefold repetition",
+        }
+    }
+}
+
+/// Result of a finished game and the rule that ended it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+impl GameOutcome {
+    const fn draw(termination: Termination) -> Self {
+        GameOutcome { result: GameResult::Draw, termination }
+    }
+
+    /// Human readable summary, e.g. "White wins by checkmate"
+    pub fn summary(&self) -> String {
+        let who = match self.result {
+            GameResult::WhiteWins => "White wins",
+            GameResult::BlackWins => "Black wins",
+            GameResult::Draw => "Draw",
+        };
+        format!("{} by {}", who, self.termination.description())
+    }
+}
+
+/// Outcome that ends the game without anyone claiming it, if any
+pub fn automatic_outcome(board: &mut Board) -> Option<GameOutcome> {
+    if generate_legal_moves(board).is_empty() {
+        return Some(if !in_check(board) {
+            GameOutcome::draw(Termination::Stalemate)
+        } else {
+            GameOutcome {
+                result: if board.side == WHITE { GameResult::BlackWins } else { GameResult::WhiteWins },
+                termination: Termination::Checkmate,
+            }
+        });
+    }
+
+    if is_insufficient_material(board) {
+        Some(GameOutcome::draw(Termination::InsufficientMaterial))
+    } else if board.halfmove >= 150 {
+        Some(GameOutcome::draw(Termination::SeventyFiveMoveRule))
+    } else if board.repetition_count() >= 4 {
+        Some(GameOutcome::draw(Termination::FivefoldRepetition))
+    } else {
+        None
+    }
+}
+
+/// Draw the side to move may claim in this position, if any
+pub fn claimable_draw(board: &Board) -> Option<GameOutcome> {
+    if board.is_threefold_repetition() {
+        Some(GameOutcome::draw(Termination::ThreefoldRepetition))
+    } else if board.halfmove >= 100 {
+        Some(GameOutcome::draw(Termination::FiftyMoveRule))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome_of(fen: &str) -> Option<GameOutcome> {
+        automatic_outcome(&mut Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn test_automatic_outcomes() {
+        assert_eq!(outcome_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+
+        let mate = outcome_of("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert_eq!(mate.result, GameResult::WhiteWins);
+        assert_eq!(mate.termination, Termination::Checkmate);
+        assert_eq!(mate.summary(), "White wins by checkmate");
+
+        let stalemate = outcome_of("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(stalemate.termination, Termination::Stalemate);
+        assert_eq!(stalemate.result, GameResult::Draw);
+
+        let bare = outcome_of("4k3/8/8/8/8/8/8/4KN2 w - - 0 1").unwrap();
+        assert_eq!(bare.termination, Termination::InsufficientMaterial);
+
+        // Bishops all on light squares (c8, f1) can't mate, but on opposite colours they can
+        let bishops = outcome_of("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1").unwrap();
+        assert_eq!(bishops.termination, Termination::InsufficientMaterial);
+        assert_eq!(outcome_of("1b2k3/8/8/8/8/8/8/4KB2 w - - 0 1"), None);
+        assert_eq!(outcome_of("2b1k3/8/8/8/8/8/8/4KBN1 w - - 0 1"), None);
+
+        let seventy_five = outcome_of("4k3/8/8/8/8/8/8/R3K3 w - - 150 120").unwrap();
+        assert_eq!(seventy_five.termination, Termination::SeventyFiveMoveRule);
+    }
+
+    #[test]
+    fn test_claimable_draws() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(claimable_draw(&board), None);
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(claimable_draw(&board).map(|o| o.termination), Some(Termination::FiftyMoveRule));
+    }
+
+    #[test]
+    fn test_result_scores() {
+        assert_eq!(GameResult::WhiteWins.score_for(WHITE), 1.0);
+        assert_eq!(GameResult::WhiteWins.score_for(BLACK), 0.0);
+        assert_eq!(GameResult::Draw.score_for(BLACK), 0.5);
+        assert_eq!(GameResult::BlackWins.as_pgn(), "0-1");
+    }
+}
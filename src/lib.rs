@@ -0,0 +1,25 @@
+//! Engine core shared by the interactive binary and the tuner (`src/bin/tune.rs`)
+
+pub mod auth;
+pub mod bench;
+pub mod board;
+pub mod crypto;
+pub mod eval;
+pub mod gamesave;
+pub mod gamestatus;
+pub mod movegen;
+pub mod nnue;
+pub mod params;
+pub mod pawns;
+pub mod perft;
+pub mod pgn;
+pub mod rating;
+pub mod san;
+pub mod search;
+pub mod see;
+pub mod timeman;
+pub mod tt;
+pub mod uci;
+pub mod utils;
+pub mod xboard;
+pub mod zobrist;
@@ -1,30 +1,51 @@
 //! Enhanced Chess Engine v2.0 with user authentication, save/load, and tips
 
-mod auth;
-mod board;
-mod eval;
-mod gamesave;
-mod movegen;
-mod perft;
-mod search;
 mod tips;
-mod tt;
 mod ui;
-mod utils;
-mod zobrist;
+
+use chess_engine::{
+    auth, bench, board, eval, gamesave, gamestatus, movegen, nnue, params, perft, pgn, san, search, uci, utils,
+    xboard,
+};
 
 use auth::{AuthManager, User};
 use board::*;
 use gamesave::{GameManager, SavedGame};
+use gamestatus::*;
 use movegen::*;
 use perft::*;
+use san::*;
 use search::*;
 use tips::TipsEngine;
+use uci::UciEngine;
 use ui::UI;
 use utils::*;
+use xboard::XboardEngine;
 
 use std::io::{self, Write};
 
+/// Computer opponent in play-versus-engine mode
+struct EngineOpponent {
+    color: u8,
+    limits: SearchLimits,
+    /// Difficulty shown in the player name, e.g. "medium" or "depth 5"
+    level: String,
+    /// Nominal Elo strength used for rating updates
+    rating: u32,
+}
+
+/// What `finish_game` counted, so `reopen_game` can take it back even if the
+/// opponent has changed since
+struct RecordedResult {
+    result: GameResult,
+    /// Colour the user played
+    color: u8,
+    /// Whether the user's rating was updated
+    rated: bool,
+    /// Registered opponent whose statistics and rating were updated as well
+    opponent: Option<User>,
+}
+
 struct GameSession {
     board: Board,
     searcher: Searcher,
@@ -35,20 +56,40 @@ struct GameSession {
     black_player: String,
     show_tips: bool,
     last_move: Option<Move>,
+    /// Set once the game has ended; moves are refused until `new` or `undo`
+    outcome: Option<GameOutcome>,
+    /// How `outcome` was counted in the players' statistics, if it was
+    recorded: Option<RecordedResult>,
+    /// User statistics changed and need to be written back
+    user_dirty: bool,
+    /// Opponent who left before their last result was taken back, still to be written back
+    departed_opponent: Option<User>,
+    /// Engine that replies automatically, or None when both sides are human
+    engine: Option<EngineOpponent>,
+    /// Second registered user playing on the same keyboard, for rated local games
+    opponent: Option<User>,
 }
 
 impl GameSession {
     fn new(user: User) -> Self {
+        // The logged-in user takes White; the opponent shares the keyboard
+        let white_player = user.username.clone();
         GameSession {
             board: Board::starting_position(),
-            searcher: Searcher::new(64),
+            searcher: Searcher::with_format(64, InfoFormat::Repl),
             game_manager: GameManager::new(),
             user,
             move_history: Vec::new(),
-            white_player: String::from("Human"),
+            white_player,
             black_player: String::from("Human"),
             show_tips: true,
             last_move: None,
+            outcome: None,
+            recorded: None,
+            user_dirty: false,
+            departed_opponent: None,
+            engine: None,
+            opponent: None,
         }
     }
 
@@ -61,42 +102,227 @@ impl GameSession {
         }
     }
 
+    /// Parse a move typed at the prompt, in coordinate notation ("g1f3") or SAN ("Nf3")
+    fn parse_move(&mut self, move_str: &str) -> Option<Move> {
+        Move::from_string(move_str)
+            .and_then(|mov| find_legal_move(&mut self.board, mov))
+            .or_else(|| parse_san(&mut self.board, move_str))
+    }
+
     fn make_move(&mut self, move_str: &str) -> bool {
-        if let Some(mov) = Move::from_string(move_str) {
-            let legal_moves = generate_legal_moves(&mut self.board);
-
-            let mut found = None;
-            for &legal_mov in &legal_moves {
-                if legal_mov.from() == mov.from() && legal_mov.to() == mov.to() {
-                    if mov.is_promotion() {
-                        if legal_mov.is_promotion() && legal_mov.promotion() == mov.promotion() {
-                            found = Some(legal_mov);
-                            break;
-                        }
-                    } else {
-                        found = Some(legal_mov);
-                        break;
-                    }
-                }
-            }
+        if let Some(outcome) = self.outcome {
+            UI::print_error(&format!("Game over: {}. Type 'new' to start again or 'undo' to take back a move",
+                outcome.summary()));
+            return false;
+        }
 
-            if let Some(legal_mov) = found {
-                self.board.make_move(legal_mov);
-                self.move_history.push(legal_mov);
-                self.last_move = Some(legal_mov);
-                UI::print_success(&format!("Move made: {}", legal_mov.to_string()));
-                return true;
-            } else {
-                UI::print_error(&format!("Illegal move: {}", move_str));
+        if self.engine_to_move() {
+            UI::print_error("It is the computer's turn");
+            return false;
+        }
+
+        if let Some(mov) = self.parse_move(move_str) {
+            let san = move_to_san(&mut self.board, mov);
+            UI::print_success(&format!("Move made: {}", san));
+            self.apply_move(mov);
+            true
+        } else {
+            UI::print_error(&format!("Illegal move: {}", move_str));
+            false
+        }
+    }
+
+    /// Play a legal move and check whether it ended the game
+    fn apply_move(&mut self, mov: Move) {
+        self.board.make_move(mov);
+        self.move_history.push(mov);
+        self.last_move = Some(mov);
+
+        if let Some(outcome) = automatic_outcome(&mut self.board) {
+            self.finish_game(outcome);
+        } else if let Some(draw) = claimable_draw(&self.board) {
+            UI::print_info(&format!("Draw available by {}: type 'claim' to claim it",
+                draw.termination.description()));
+        }
+    }
+
+    /// Whether the computer opponent should move next
+    fn engine_to_move(&self) -> bool {
+        self.outcome.is_none() && self.engine.as_ref().is_some_and(|e| e.color == self.board.side)
+    }
+
+    /// Let the computer opponent reply if it is its turn
+    fn engine_reply(&mut self) {
+        if !self.engine_to_move() {
+            return;
+        }
+        let limits = match &self.engine {
+            Some(engine) => engine.limits,
+            None => return,
+        };
+
+        UI::print_info("Computer is thinking...");
+        self.searcher.info_format = InfoFormat::Silent;
+        let mov = self.searcher.search(&self.board, limits);
+        self.searcher.info_format = InfoFormat::Repl;
+
+        let san = move_to_san(&mut self.board, mov);
+        UI::print_success(&format!("Computer plays: {}", san));
+        self.apply_move(mov);
+    }
+
+    /// Start a new game against the computer, which plays `engine.color`
+    fn start_engine_game(&mut self, engine: EngineOpponent) {
+        let computer = format!("Computer ({})", engine.level);
+        UI::print_info(&format!("{} is rated {}", computer, engine.rating));
+        if engine.color == BLACK {
+            self.white_player = self.user.username.clone();
+            self.black_player = computer;
+        } else {
+            self.white_player = computer;
+            self.black_player = self.user.username.clone();
+        }
+
+        self.engine = Some(engine);
+        self.opponent = None;
+        self.reset_game(Board::starting_position(), Vec::new());
+        UI::print_success(&format!("New game: {} vs {}", self.white_player, self.black_player));
+    }
+
+    /// Start a rated game against another registered user, who takes Black
+    fn start_local_game(&mut self, opponent: User) {
+        self.white_player = self.user.username.clone();
+        self.black_player = opponent.username.clone();
+        self.opponent = Some(opponent);
+        self.engine = None;
+        self.reset_game(Board::starting_position(), Vec::new());
+        UI::print_success(&format!("New rated game: {} vs {}", self.white_player, self.black_player));
+    }
+
+    /// Go back to an unrated game against an anonymous player
+    fn stop_local_game(&mut self) {
+        if let Some(opponent) = self.opponent.take() {
+            self.black_player = String::from("Human");
+            UI::print_success(&format!("{} left the game", opponent.username));
+        } else {
+            UI::print_info("Not playing against another user");
+        }
+    }
+
+    /// Go back to two human players sharing the keyboard
+    fn stop_engine_game(&mut self) {
+        if let Some(engine) = self.engine.take() {
+            let opponent = if engine.color == WHITE { &mut self.white_player } else { &mut self.black_player };
+            *opponent = String::from("Human");
+            UI::print_success(&format!("Computer opponent ({}) switched off", engine.level));
+        } else {
+            UI::print_info("Not playing against the computer");
+        }
+    }
+
+    /// Claim a draw by threefold repetition or the 50-move rule
+    fn claim_draw(&mut self) -> bool {
+        if self.outcome.is_some() {
+            UI::print_error("The game is already over");
+            return false;
+        }
+
+        match claimable_draw(&self.board) {
+            Some(draw) => {
+                self.finish_game(draw);
+                true
             }
+            None => {
+                UI::print_error("No draw to claim: position has not occurred three times and the 50-move rule does not apply");
+                false
+            }
+        }
+    }
+
+    /// Colour the logged-in user plays in this game, if any
+    fn user_color(&self) -> Option<u8> {
+        if self.white_player == self.user.username {
+            Some(WHITE)
+        } else if self.black_player == self.user.username {
+            Some(BLACK)
         } else {
-            UI::print_error(&format!("Invalid move format: {}", move_str));
+            None
+        }
+    }
+
+    /// Name and rating of the opponent when the game is rated
+    fn rated_opponent(&self) -> Option<(String, u32)> {
+        if let Some(engine) = &self.engine {
+            Some((format!("Computer ({})", engine.level), engine.rating))
+        } else {
+            self.opponent.as_ref().map(|o| (o.username.clone(), o.rating))
+        }
+    }
+
+    /// End the game: announce the result and count it in the players' statistics and ratings
+    fn finish_game(&mut self, outcome: GameOutcome) {
+        self.outcome = Some(outcome);
+        UI::print_success(&format!("Game over: {} ({})", outcome.summary(), outcome.result.as_pgn()));
+
+        if let Some(color) = self.user_color() {
+            let rated = self.rated_opponent();
+            let rating_before = self.user.rating;
+            self.user.record_game(outcome.result, color, rated.as_ref().map(|(name, r)| (name.as_str(), *r)));
+
+            if rated.is_some() {
+                UI::print_info(&format!("{}: rating {} -> {}", self.user.username, rating_before, self.user.rating));
+            }
+            if let Some(opponent) = &mut self.opponent {
+                let opponent_before = opponent.rating;
+                opponent.record_game(outcome.result, color ^ 1, Some((&self.user.username, rating_before)));
+                UI::print_info(&format!("{}: rating {} -> {}", opponent.username, opponent_before, opponent.rating));
+            }
+
+            self.recorded = Some(RecordedResult {
+                result: outcome.result,
+                color,
+                rated: rated.is_some(),
+                opponent: self.opponent.clone(),
+            });
+            self.user_dirty = true;
+        }
+    }
+
+    /// Reopen a finished game, taking its result back out of the statistics
+    fn reopen_game(&mut self) {
+        self.outcome = None;
+        let Some(recorded) = self.recorded.take() else {
+            return;
+        };
+
+        self.user.unrecord_game(recorded.result, recorded.color, recorded.rated);
+        if let Some(mut opponent) = recorded.opponent {
+            // The opponent may have left the game since
+            match self.opponent.as_mut().filter(|o| o.username == opponent.username) {
+                Some(current) => current.unrecord_game(recorded.result, recorded.color ^ 1, true),
+                None => {
+                    opponent.unrecord_game(recorded.result, recorded.color ^ 1, true);
+                    self.departed_opponent = Some(opponent);
+                }
+            }
         }
-        false
+        self.user_dirty = true;
+    }
+
+    /// Replace the game with a new position. Finished positions are marked as
+    /// over but not counted in the statistics.
+    fn reset_game(&mut self, board: Board, moves: Vec<Move>) {
+        self.board = board;
+        self.move_history = moves;
+        self.last_move = self.move_history.last().copied();
+        self.searcher.tt.clear();
+        self.outcome = automatic_outcome(&mut self.board);
+        self.recorded = None;
     }
 
     fn undo_move(&mut self) -> bool {
         if !self.board.history.is_empty() {
+            self.reopen_game();
             self.board.unmake_move();
             self.move_history.pop();
             if !self.move_history.is_empty() {
@@ -104,6 +330,13 @@ impl GameSession {
             } else {
                 self.last_move = None;
             }
+
+            // Against the computer, take back its reply as well so the user is on move
+            if self.engine_to_move() && !self.board.history.is_empty() {
+                self.board.unmake_move();
+                self.move_history.pop();
+                self.last_move = self.move_history.last().copied();
+            }
             UI::print_success("Move undone");
             true
         } else {
@@ -115,10 +348,12 @@ impl GameSession {
     fn save_game(&self) -> Result<(), String> {
         let saved_game = SavedGame::new(
             self.user.username.clone(),
+            &self.starting_board(),
             &self.board,
             &self.move_history,
             self.white_player.clone(),
             self.black_player.clone(),
+            self.outcome,
         );
 
         match self.game_manager.save_game(&saved_game) {
@@ -144,7 +379,8 @@ impl GameSession {
         println!("\n╔══════════════════ SAVED GAMES ═══════════════════╗");
         for (i, (filename, game)) in saves.iter().enumerate() {
             println!("║ {}. {} vs {}                    ", i + 1, game.white_player, game.black_player);
-            println!("║    {} moves - {}              ", game.moves.len(), filename);
+            let result = game.outcome.map_or("*", |o| o.result.as_pgn());
+            println!("║    {} moves, {} - {}              ", game.moves.len(), result, filename);
         }
         println!("╚═════════════════════════════════════════════════╝\n");
 
@@ -155,15 +391,17 @@ impl GameSession {
                 let (filename, _) = &saves[index - 1];
                 match self.game_manager.load_game(filename) {
                     Ok(saved_game) => {
-                        match Board::from_fen(&saved_game.fen) {
+                        match saved_game.replay() {
                             Ok(board) => {
-                                self.board = board;
+                                let moves = board.history.iter().map(|h| h.mov).collect();
+                                self.reset_game(board, moves);
+                                self.outcome = saved_game.outcome;
+                                self.engine = None;
+                                self.opponent = None;
                                 self.white_player = saved_game.white_player;
                                 self.black_player = saved_game.black_player;
-                                self.move_history.clear();
-                                // Restore move history if possible
-                                UI::print_success(&format!("Game loaded: {} vs {}", 
-                                    self.white_player, self.black_player));
+                                UI::print_success(&format!("Game loaded: {} vs {} ({} moves)",
+                                    self.white_player, self.black_player, self.move_history.len()));
                             }
                             Err(e) => UI::print_error(&e),
                         }
@@ -174,11 +412,116 @@ impl GameSession {
         }
     }
 
+    /// Position the current game started from (the board with all moves taken back)
+    fn starting_board(&self) -> Board {
+        let mut start = self.board.clone();
+        while !start.history.is_empty() {
+            start.unmake_move();
+        }
+        start
+    }
+
+    fn export_pgn(&self, path: &str) {
+        let start = self.starting_board();
+        let result = self.outcome.map_or("*", |o| o.result.as_pgn());
+        let game = pgn::game_from_moves(
+            &start,
+            &self.move_history,
+            &self.white_player,
+            &self.black_player,
+            &gamesave::chrono_timestamp(),
+            result,
+        );
+
+        match game.to_pgn().and_then(|text| {
+            std::fs::write(path, text).map_err(|e| format!("Failed to write file: {}", e))
+        }) {
+            Ok(()) => UI::print_success(&format!("Game exported to {}", path)),
+            Err(e) => UI::print_error(&e),
+        }
+    }
+
+    fn import_pgn(&mut self, path: &str, index: usize) {
+        let games = match std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|text| pgn::parse_pgn(&text))
+        {
+            Ok(games) => games,
+            Err(e) => {
+                UI::print_error(&e);
+                return;
+            }
+        };
+
+        if index == 0 || index > games.len() {
+            UI::print_error(&format!("{} contains {} game(s)", path, games.len()));
+            return;
+        }
+
+        let game = &games[index - 1];
+        match game.replay() {
+            Ok(board) => {
+                self.reset_game(board, game.moves.clone());
+                self.engine = None;
+                self.opponent = None;
+                self.white_player = game.tag("White").unwrap_or("?").to_string();
+                self.black_player = game.tag("Black").unwrap_or("?").to_string();
+                UI::print_success(&format!("Imported game {} of {}: {} vs {} ({} moves, {})",
+                    index, games.len(), self.white_player, self.black_player,
+                    game.moves.len(), game.result));
+            }
+            Err(e) => UI::print_error(&e),
+        }
+    }
+
     fn show_stats(&self) {
         UI::print_user_info(&self.user);
+        UI::print_rating_history(&self.user, 10);
     }
 }
 
+/// Parse a computer difficulty: a preset (easy, medium, hard) or an explicit
+/// `depth N`, `movetime MS` or `nodes N` budget, optionally followed by
+/// `elo N` to set the nominal strength used for rating the game
+fn parse_difficulty(color: u8, args: &[&str]) -> Result<EngineOpponent, String> {
+    let (args, elo) = match args {
+        [rest @ .., "elo", n] => (rest, Some(n.parse::<u32>().map_err(|_| format!("Invalid Elo: {}", n))?)),
+        _ => (args, None),
+    };
+
+    let mut limits = SearchLimits::default();
+    let rating = match args {
+        [] | ["medium"] => {
+            limits.depth = Some(4);
+            1400
+        }
+        ["easy"] => {
+            limits.depth = Some(2);
+            1000
+        }
+        ["hard"] => {
+            limits.movetime = Some(2000);
+            1800
+        }
+        ["depth", n] => {
+            limits.depth = Some(n.parse().map_err(|_| format!("Invalid depth: {}", n))?);
+            1500
+        }
+        ["movetime", ms] => {
+            limits.movetime = Some(ms.parse().map_err(|_| format!("Invalid movetime: {}", ms))?);
+            1500
+        }
+        ["nodes", n] => {
+            limits.nodes = Some(n.parse().map_err(|_| format!("Invalid node count: {}", n))?);
+            1500
+        }
+        _ => return Err(format!("Unknown difficulty: {}", args.join(" "))),
+    };
+
+    let level = if args.is_empty() { "medium".to_string() } else { args.join(" ") };
+    Ok(EngineOpponent { color, limits, level, rating: elo.unwrap_or(rating) })
+}
+
 fn login_or_register(auth_manager: &mut AuthManager) -> Option<User> {
     UI::clear_screen();
     UI::print_banner();
@@ -191,6 +534,18 @@ fn login_or_register(auth_manager: &mut AuthManager) -> Option<User> {
         let choice = UI::prompt("Choose an option: ");
 
         match choice.as_str() {
+            // A GUI talking UCI sends "uci" straight away
+            "uci" => {
+                let mut engine = UciEngine::new();
+                engine.handle_command("uci");
+                engine.run();
+                std::process::exit(0);
+            }
+            // ...and an XBoard/CECP GUI sends "xboard"
+            "xboard" => {
+                XboardEngine::new().run();
+                std::process::exit(0);
+            }
             "1" => {
                 let username = UI::prompt("Username: ");
                 let password = UI::prompt_password("Password: ");
@@ -228,8 +583,61 @@ fn login_or_register(auth_manager: &mut AuthManager) -> Option<User> {
 }
 
 fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    // `--params FILE` loads evaluation weights for every mode
+    if let Some(i) = args.iter().position(|arg| arg == "--params") {
+        match args.get(i + 1).map(|path| params::EvalParams::load(path)) {
+            Some(Ok(loaded)) => params::set_active_params(Some(loaded)),
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("Usage: --params <file.json>");
+                std::process::exit(1);
+            }
+        }
+        args.drain(i..(i + 2));
+    }
+
+    if args.iter().any(|arg| arg == "uci" || arg == "--uci") {
+        UciEngine::new().run();
+        return;
+    }
+    if args.iter().any(|arg| arg == "xboard" || arg == "--xboard") {
+        XboardEngine::new().run();
+        return;
+    }
+    if args.first().is_some_and(|arg| arg == "bench" || arg == "--bench") {
+        let depth = args.get(1).and_then(|d| d.parse().ok()).unwrap_or(bench::DEFAULT_BENCH_DEPTH);
+        bench::run_bench(depth);
+        return;
+    }
+
     let mut auth_manager = AuthManager::new();
 
+    // Accounts from before passwords were stored get one set here
+    if args.first().is_some_and(|arg| arg == "reset-password") {
+        let Some(username) = args.get(1) else {
+            eprintln!("Usage: reset-password <user>");
+            std::process::exit(1);
+        };
+        let password = UI::prompt_password("New password (min 4 chars): ");
+        if password != UI::prompt_password("Confirm password: ") {
+            eprintln!("Passwords don't match");
+            std::process::exit(1);
+        }
+        match auth_manager.reset_password(username, &password) {
+            Ok(()) => println!("Password set for {}", username),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     loop {
         let user = match login_or_register(&mut auth_manager) {
             Some(u) => u,
@@ -254,6 +662,17 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
     session.display_board();
 
     loop {
+        if session.user_dirty {
+            auth_manager.update_user(&session.user);
+            if let Some(opponent) = &session.opponent {
+                auth_manager.update_user(opponent);
+            }
+            if let Some(opponent) = session.departed_opponent.take() {
+                auth_manager.update_user(&opponent);
+            }
+            session.user_dirty = false;
+        }
+
         print!("\n{}> ", session.user.username);
         io::stdout().flush().unwrap();
 
@@ -282,11 +701,9 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
             }
 
             "new" => {
-                session.board = Board::starting_position();
-                session.searcher.tt.clear();
-                session.move_history.clear();
-                session.last_move = None;
+                session.reset_game(Board::starting_position(), Vec::new());
                 UI::print_success("New game started");
+                session.engine_reply();
                 session.display_board();
             }
 
@@ -297,6 +714,7 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
             "move" | "m" => {
                 if parts.len() > 1 {
                     if session.make_move(parts[1]) {
+                        session.engine_reply();
                         session.display_board();
                     }
                 } else {
@@ -310,6 +728,61 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
                 }
             }
 
+            "play" => {
+                let color = match parts.get(1).map(|c| c.to_lowercase()).as_deref() {
+                    Some("white") | Some("w") => WHITE,
+                    Some("black") | Some("b") => BLACK,
+                    Some("off") => {
+                        session.stop_engine_game();
+                        continue;
+                    }
+                    _ => {
+                        UI::print_error("Usage: play white|black [easy|medium|hard|depth N|movetime MS|nodes N] [elo N] | play off");
+                        continue;
+                    }
+                };
+
+                match parse_difficulty(color ^ 1, &parts[2..]) {
+                    Ok(engine) => {
+                        session.start_engine_game(engine);
+                        session.engine_reply();
+                        session.display_board();
+                    }
+                    Err(e) => UI::print_error(&e),
+                }
+            }
+
+            "versus" | "vs" => {
+                let name = match parts.get(1) {
+                    Some(&"off") => {
+                        session.stop_local_game();
+                        continue;
+                    }
+                    Some(name) => *name,
+                    None => {
+                        UI::print_error("Usage: versus <username> | versus off");
+                        continue;
+                    }
+                };
+                if name == session.user.username {
+                    UI::print_error("You cannot play a rated game against yourself");
+                    continue;
+                }
+
+                let password = UI::prompt_password(&format!("{}'s password: ", name));
+                match auth_manager.login(name, &password) {
+                    Ok(opponent) => {
+                        session.start_local_game(opponent);
+                        session.display_board();
+                    }
+                    Err(e) => UI::print_error(&e),
+                }
+            }
+
+            "claim" => {
+                session.claim_draw();
+            }
+
             "hint" => {
                 let hint = TipsEngine::get_hint(&mut session.board);
                 UI::print_tip(&hint);
@@ -333,6 +806,24 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
                 session.display_board();
             }
 
+            "export" => {
+                if parts.len() < 2 {
+                    UI::print_error("Usage: export <file.pgn>");
+                    continue;
+                }
+                session.export_pgn(parts[1]);
+            }
+
+            "import" => {
+                if parts.len() < 2 {
+                    UI::print_error("Usage: import <file.pgn> [game number]");
+                    continue;
+                }
+                let index = parts.get(2).and_then(|n| n.parse().ok()).unwrap_or(1);
+                session.import_pgn(parts[1], index);
+                session.display_board();
+            }
+
             "stats" | "profile" => {
                 session.show_stats();
             }
@@ -344,36 +835,25 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
             }
 
             "go" => {
-                if parts.len() < 3 {
-                    UI::print_error("Usage: go depth <n> | go movetime <ms>");
+                let limits = SearchLimits::parse(&parts[1..]);
+                if !limits.is_limited() {
+                    UI::print_error("Usage: go depth <n> | movetime <ms> | nodes <n> | wtime <ms> btime <ms> [winc <ms> binc <ms>] [movestogo <n>]");
                     continue;
                 }
 
-                let limits = match parts[1] {
-                    "depth" => {
-                        let depth = parts[2].parse::<u8>().unwrap_or(6);
-                        SearchLimits {
-                            depth: Some(depth),
-                            movetime: None,
-                            nodes: None,
-                        }
-                    }
-                    "movetime" => {
-                        let movetime = parts[2].parse::<u128>().unwrap_or(1000);
-                        SearchLimits {
-                            depth: None,
-                            movetime: Some(movetime),
-                            nodes: None,
-                        }
-                    }
-                    _ => {
-                        UI::print_error(&format!("Unknown go option: {}", parts[1]));
-                        continue;
-                    }
-                };
+                if generate_legal_moves(&mut session.board).is_empty() {
+                    UI::print_info("No legal moves in this position");
+                    continue;
+                }
+
+                let best_move = session.searcher.search(&session.board, limits);
+                let san = move_to_san(&mut session.board, best_move);
+                println!("bestmove {} ({})", best_move.to_string(), san);
+            }
 
-                let best_move = session.searcher.search(&mut session.board, limits);
-                println!("bestmove {}", best_move.to_string());
+            "bench" => {
+                let depth = parts.get(1).and_then(|d| d.parse().ok()).unwrap_or(bench::DEFAULT_BENCH_DEPTH);
+                bench::run_bench(depth);
             }
 
             "perft" => {
@@ -395,18 +875,71 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
                 UI::print_info(&format!("Nodes: {} Time: {:.3}s NPS: {}", nodes, elapsed, nps));
             }
 
+            "nnue" => match parts.get(1) {
+                None => match &session.searcher.nnue {
+                    Some(net) => UI::print_info(&format!("NNUE evaluation on ({} hidden neurons)", net.hidden_size())),
+                    None => UI::print_info("Classical evaluation (nnue <file> loads a network)"),
+                },
+                Some(&"off") => {
+                    session.searcher.nnue = None;
+                    UI::print_success("Switched to the classical evaluation");
+                }
+                Some(path) => match nnue::Network::load(path) {
+                    Ok(net) => {
+                        session.searcher.nnue = Some(std::sync::Arc::new(net));
+                        UI::print_success(&format!("Loaded network {}", path));
+                    }
+                    Err(e) => UI::print_error(&e),
+                },
+            },
+
+            "params" => match (parts.get(1).copied(), parts.get(2)) {
+                (Some("load"), Some(path)) => match params::EvalParams::load(path) {
+                    Ok(loaded) => {
+                        params::set_active_params(Some(loaded));
+                        UI::print_success(&format!("Loaded evaluation weights from {}", path));
+                    }
+                    Err(e) => UI::print_error(&e),
+                },
+                (Some("save"), Some(path)) => match params::active_params().save(path) {
+                    Ok(()) => UI::print_success(&format!("Saved evaluation weights to {}", path)),
+                    Err(e) => UI::print_error(&e),
+                },
+                (Some("reset"), None) => {
+                    params::set_active_params(None);
+                    UI::print_success("Restored the built-in evaluation weights");
+                }
+                _ => UI::print_error("Usage: params load <file> | params save <file> | params reset"),
+            },
+
             "eval" | "e" => {
-                let score = eval::evaluate(&session.board);
-                UI::print_info(&format!("Evaluation: {} centipawns (from {} perspective)",
-                    score,
-                    if session.board.side == WHITE { "white" } else { "black" }));
+                let mut board = session.board.clone();
+                board.set_nnue(session.searcher.nnue.clone());
+                match (parts.get(1).copied(), parts.get(2).copied()) {
+                    (None, _) => {
+                        let score = eval::evaluate(&board, &params::active_params());
+                        UI::print_info(&format!("Evaluation: {} centipawns (from {} perspective)",
+                            score,
+                            if session.board.side == WHITE { "white" } else { "black" }));
+                    }
+                    (Some("trace"), None) => {
+                        println!("\n{}", eval::trace(&board, &params::active_params()).to_text());
+                    }
+                    (Some("trace"), Some("json")) => {
+                        match serde_json::to_string_pretty(&eval::trace(&board, &params::active_params())) {
+                            Ok(json) => println!("{}", json),
+                            Err(e) => UI::print_error(&format!("Failed to serialize the trace: {}", e)),
+                        }
+                    }
+                    _ => UI::print_error("Usage: eval [trace [json]]"),
+                }
             }
 
             "legal" => {
                 let legal_moves = generate_legal_moves(&mut session.board);
                 println!("\n Legal moves ({}):", legal_moves.len());
-                for (i, mov) in legal_moves.iter().enumerate() {
-                    print!("{} ", mov.to_string());
+                for (i, &mov) in legal_moves.iter().enumerate() {
+                    print!("{} ", move_to_san(&mut session.board, mov));
                     if (i + 1) % 8 == 0 {
                         println!();
                     }
@@ -421,11 +954,12 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
                     let fen = parts[1..].join(" ");
                     match Board::from_fen(&fen) {
                         Ok(new_board) => {
-                            session.board = new_board;
-                            session.searcher.tt.clear();
-                            session.move_history.clear();
-                            session.last_move = None;
+                            session.reset_game(new_board, Vec::new());
                             UI::print_success("Position loaded");
+                            if let Some(outcome) = session.outcome {
+                                UI::print_info(&format!("Game over in this position: {}", outcome.summary()));
+                            }
+                            session.engine_reply();
                             session.display_board();
                         }
                         Err(e) => UI::print_error(&e),
@@ -434,13 +968,16 @@ fn run_game_loop(auth_manager: &mut AuthManager, mut user: User) {
             }
 
             _ => {
-                // Try to parse as a move
-                if let Some(_mov) = Move::from_string(&command) {
-                    if session.make_move(&command) {
+                // Try to parse as a move (coordinate notation or SAN, so keep the original case)
+                // After the game ends nothing is legal, so recognise coordinate moves by shape
+                let looks_like_move = session.outcome.is_some() && Move::from_string(parts[0]).is_some();
+                if looks_like_move || session.parse_move(parts[0]).is_some() {
+                    if session.make_move(parts[0]) {
+                        session.engine_reply();
                         session.display_board();
                     }
                 } else {
-                    UI::print_error(&format!("Unknown command: {}", command));
+                    UI::print_error(&format!("Unknown command or illegal move: {}", parts[0]));
                     UI::print_info("Type 'help' for available commands");
                 }
             }
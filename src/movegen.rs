@@ -259,6 +259,22 @@ pub fn is_square_attacked(board: &Board, sq: u8, by_color: u8) -> bool {
     false
 }
 
+/// Pieces of both colours attacking `sq`, with sliders blocked only by `occupied`.
+/// Passing a reduced occupancy reveals x-ray attackers behind removed pieces.
+pub fn attackers_to(board: &Board, sq: u8, occupied: u64) -> u64 {
+    let [white, black] = &board.pieces;
+    let bishops = white[BISHOP as usize] | black[BISHOP as usize];
+    let rooks = white[ROOK as usize] | black[ROOK as usize];
+    let queens = white[QUEEN as usize] | black[QUEEN as usize];
+
+    (pawn_attacks(sq, BLACK) & white[PAWN as usize])
+        | (pawn_attacks(sq, WHITE) & black[PAWN as usize])
+        | (knight_attacks(sq) & (white[KNIGHT as usize] | black[KNIGHT as usize]))
+        | (king_attacks(sq) & (white[KING as usize] | black[KING as usize]))
+        | (bishop_attacks(sq, occupied) & (bishops | queens))
+        | (rook_attacks(sq, occupied) & (rooks | queens))
+}
+
 /// Check if current side is in check
 pub fn in_check(board: &Board) -> bool {
     let king_sq = lsb(board.pieces[board.side as usize][KING as usize]);
@@ -268,6 +284,13 @@ pub fn in_check(board: &Board) -> bool {
     is_square_attacked(board, king_sq, board.side ^ 1)
 }
 
+/// Check if the side that just moved left its own king in check (call after `make_move`)
+#[inline]
+pub fn left_in_check(board: &Board) -> bool {
+    let king_sq = lsb(board.pieces[(board.side ^ 1) as usize][KING as usize]);
+    king_sq >= 64 || is_square_attacked(board, king_sq, board.side)
+}
+
 /// Generate pseudo-legal moves
 pub fn generate_moves(board: &Board, moves: &mut Vec<Move>) {
     let color = board.side;
@@ -582,18 +605,10 @@ pub fn generate_legal_moves(board: &mut Board) -> Vec<Move> {
     let mut pseudo_legal = Vec::with_capacity(64);
     generate_moves(board, &mut pseudo_legal);
     
-    let original_side = board.side;
     let mut legal = Vec::with_capacity(pseudo_legal.len());
     for mov in pseudo_legal {
         board.make_move(mov);
-        // After make_move, side has switched, so check if original side's king is attacked
-        let king_sq = lsb(board.pieces[original_side as usize][KING as usize]);
-        let is_legal = if king_sq < 64 {
-            !is_square_attacked(board, king_sq, board.side)
-        } else {
-            false
-        };
-        if is_legal {
+        if !left_in_check(board) {
             legal.push(mov);
         }
         board.unmake_move();
@@ -601,3 +616,14 @@ pub fn generate_legal_moves(board: &mut Board) -> Vec<Move> {
     
     legal
 }
+
+/// Find the legal move matching a parsed coordinate move (e.g. from `Move::from_string`).
+/// The returned move carries the proper castling/en-passant flags.
+pub fn find_legal_move(board: &mut Board, mov: Move) -> Option<Move> {
+    generate_legal_moves(board).into_iter().find(|legal_mov| {
+        legal_mov.from() == mov.from()
+            && legal_mov.to() == mov.to()
+            && legal_mov.is_promotion() == mov.is_promotion()
+            && (!mov.is_promotion() || legal_mov.promotion() == mov.promotion())
+    })
+}
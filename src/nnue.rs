@@ -0,0 +1,381 @@
+//! Optional NNUE evaluation: a perspective network over 768 piece-square
+//! features, with accumulators updated incrementally as moves are made
+//!
+//! Each side has its own accumulator: a hidden layer of `hidden` neurons fed by
+//! the pieces as seen from that side (own/enemy pieces, board flipped for
+//! black). The output neuron reads the side to move's accumulator followed by
+//! the other one, after clipping both to `[0, QA]`.
+//!
+//! Network files are little-endian:
+//!
+//! | field           | type  | count          |
+//! |-----------------|-------|----------------|
+//! | magic `CENN`    | bytes | 4              |
+//! | version (1)     | u32   | 1              |
+//! | hidden size     | u32   | 1              |
+//! | feature weights | i16   | 768 × hidden   |
+//! | feature biases  | i16   | hidden         |
+//! | output weights  | i16   | 2 × hidden     |
+//! | output bias     | i16   | 1              |
+//!
+//! Feature weights are quantised by `QA` and output weights by `QB`; the
+//! output is scaled to centipawns by `SCALE / (QA × QB)` and clamped to `±MAX_EVAL`.
+
+use crate::board::*;
+use crate::utils::*;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"CENN";
+const VERSION: u32 = 1;
+/// Inputs: 2 colours (own/enemy) × 6 piece types × 64 squares
+pub const FEATURES: usize = 768;
+const MAX_HIDDEN: usize = 4096;
+
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i64 = 400;
+/// Bound on the evaluation, well inside the search's mate scores, so a network
+/// with extreme weights can never produce a static eval that looks like a mate
+const MAX_EVAL: i64 = 20_000;
+
+/// Network weights, read-only and shared by every board that uses them
+pub struct Network {
+    hidden: usize,
+    /// `FEATURES` rows of `hidden` weights
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    /// `hidden` weights for the side to move, then `hidden` for the other side
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    /// Load a network file in the format described in the module docs
+    pub fn load(path: &str) -> Result<Network, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read network {}: {}", path, e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, String> {
+        if bytes.len() < 12 || &bytes[..4] != MAGIC {
+            return Err("Not a network file (bad magic)".to_string());
+        }
+        let word = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        if word(4) != VERSION {
+            return Err(format!("Unsupported network version {}", word(4)));
+        }
+        let hidden = word(8) as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(format!("Invalid hidden layer size {}", hidden));
+        }
+
+        let expected = FEATURES * hidden + hidden + 2 * hidden + 1;
+        let values: Vec<i16> = bytes[12..]
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        if values.len() != expected || !bytes.len().is_multiple_of(2) {
+            return Err(format!("Network has {} weights, expected {}", values.len(), expected));
+        }
+
+        let (feature_weights, rest) = values.split_at(FEATURES * hidden);
+        let (feature_bias, rest) = rest.split_at(hidden);
+        let (output_weights, rest) = rest.split_at(2 * hidden);
+        Ok(Network {
+            hidden,
+            feature_weights: feature_weights.to_vec(),
+            feature_bias: feature_bias.to_vec(),
+            output_weights: output_weights.to_vec(),
+            output_bias: rest[0],
+        })
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    #[inline]
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+/// Input index of a piece seen from `perspective`
+#[inline]
+fn feature(perspective: u8, piece: u8, color: u8, sq: u8) -> usize {
+    let (relative_color, sq) = if perspective == WHITE {
+        (color, sq)
+    } else {
+        (color ^ 1, sq ^ 56)
+    };
+    (relative_color as usize * 6 + piece as usize) * 64 + sq as usize
+}
+
+/// A piece placed on or removed from a square
+#[derive(Clone, Copy)]
+pub struct PieceSquare {
+    pub piece: u8,
+    pub color: u8,
+    pub sq: u8,
+}
+
+/// Accumulators for one board: a stack with one pair per move made since the
+/// last refresh, so unmaking a move is a pop
+#[derive(Clone)]
+pub struct NnueState {
+    net: Arc<Network>,
+    /// Each entry is `2 × hidden` values: white's accumulator, then black's
+    stack: Vec<i16>,
+}
+
+impl NnueState {
+    pub fn new(net: Arc<Network>, board: &Board) -> Self {
+        let mut state = NnueState { stack: Vec::with_capacity(256 * 2 * net.hidden), net };
+        state.refresh(board);
+        state
+    }
+
+    /// Rebuild the accumulators from scratch, discarding the stack
+    pub fn refresh(&mut self, board: &Board) {
+        let hidden = self.net.hidden;
+        self.stack.clear();
+        self.stack.extend_from_slice(&self.net.feature_bias);
+        self.stack.extend_from_slice(&self.net.feature_bias);
+
+        for color in [WHITE, BLACK] {
+            for piece in PAWN..=KING {
+                let mut pieces = board.pieces[color as usize][piece as usize];
+                while pieces != 0 {
+                    let sq = pop_lsb(&mut pieces);
+                    for perspective in [WHITE, BLACK] {
+                        let acc = &mut self.stack[perspective as usize * hidden..][..hidden];
+                        add_weights(acc, self.net.weights(feature(perspective, piece, color, sq)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Push accumulators for the position after a move, given the pieces it
+    /// removed and added
+    pub fn push(&mut self, removed: &[PieceSquare], added: &[PieceSquare]) {
+        let hidden = self.net.hidden;
+        let top = self.stack.len() - 2 * hidden;
+        self.stack.extend_from_within(top..);
+
+        let current = self.stack.len() - 2 * hidden;
+        for perspective in [WHITE, BLACK] {
+            let acc = &mut self.stack[current + perspective as usize * hidden..][..hidden];
+            for p in removed {
+                sub_weights(acc, self.net.weights(feature(perspective, p.piece, p.color, p.sq)));
+            }
+            for p in added {
+                add_weights(acc, self.net.weights(feature(perspective, p.piece, p.color, p.sq)));
+            }
+        }
+    }
+
+    /// Drop the accumulators of the last move. Returns false if the stack only
+    /// held the position it was refreshed from, which then needs a refresh.
+    pub fn pop(&mut self) -> bool {
+        let size = 2 * self.net.hidden;
+        if self.stack.len() <= size {
+            return false;
+        }
+        self.stack.truncate(self.stack.len() - size);
+        true
+    }
+
+    /// Accumulator of `perspective` for the current position
+    fn accumulator(&self, perspective: u8) -> &[i16] {
+        let hidden = self.net.hidden;
+        &self.stack[self.stack.len() - 2 * hidden + perspective as usize * hidden..][..hidden]
+    }
+
+    /// Evaluation in centipawns from the side to move's point of view
+    pub fn evaluate(&self, side: u8) -> i32 {
+        let hidden = self.net.hidden;
+        let (us_weights, them_weights) = self.net.output_weights.split_at(hidden);
+        let output = crelu_dot(self.accumulator(side), us_weights)
+            + crelu_dot(self.accumulator(side ^ 1), them_weights)
+            + self.net.output_bias as i64;
+        (output * SCALE / (QA * QB) as i64).clamp(-MAX_EVAL, MAX_EVAL) as i32
+    }
+}
+
+#[inline]
+fn add_weights(acc: &mut [i16], weights: &[i16]) {
+    for (a, &w) in acc.iter_mut().zip(weights) {
+        *a = a.wrapping_add(w);
+    }
+}
+
+#[inline]
+fn sub_weights(acc: &mut [i16], weights: &[i16]) {
+    for (a, &w) in acc.iter_mut().zip(weights) {
+        *a = a.wrapping_sub(w);
+    }
+}
+
+/// Sum of `clamp(acc, 0, QA) × weight`, vectorised with AVX2 when the CPU has it.
+/// Summed in 64 bits: with `MAX_HIDDEN` neurons and extreme weights the total
+/// doesn't fit in an i32.
+#[inline]
+fn crelu_dot(acc: &[i16], weights: &[i16]) -> i64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if acc.len().is_multiple_of(16) && std::arch::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was checked above
+            return unsafe { crelu_dot_avx2(acc, weights) };
+        }
+    }
+    crelu_dot_scalar(acc, weights)
+}
+
+/// Portable version, for any CPU and hidden sizes that aren't a multiple of 16
+fn crelu_dot_scalar(acc: &[i16], weights: &[i16]) -> i64 {
+    acc.iter()
+        .zip(weights)
+        .map(|(&a, &w)| ((a as i32).clamp(0, QA) * w as i32) as i64)
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn crelu_dot_avx2(acc: &[i16], weights: &[i16]) -> i64 {
+    use std::arch::x86_64::*;
+
+    let zero = _mm256_setzero_si256();
+    let max = _mm256_set1_epi16(QA as i16);
+    let mut sum = _mm256_setzero_si256();
+    for (a, w) in acc.chunks_exact(16).zip(weights.chunks_exact(16)) {
+        let a = _mm256_loadu_si256(a.as_ptr() as *const __m256i);
+        let w = _mm256_loadu_si256(w.as_ptr() as *const __m256i);
+        let clipped = _mm256_min_epi16(_mm256_max_epi16(a, zero), max);
+        // 255 × 32768 fits in i32 twice over, so the pairwise sums can't
+        // overflow; they are widened to i64 before accumulating
+        let pairs = _mm256_madd_epi16(clipped, w);
+        sum = _mm256_add_epi64(sum, _mm256_cvtepi32_epi64(_mm256_castsi256_si128(pairs)));
+        sum = _mm256_add_epi64(sum, _mm256_cvtepi32_epi64(_mm256_extracti128_si256(pairs, 1)));
+    }
+
+    let mut lanes = [0i64; 4];
+    _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+    lanes.iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::find_legal_move;
+
+    /// A small network with pseudo-random weights
+    fn test_network(hidden: usize) -> Network {
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = |range: i64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            ((seed % (2 * range as u64 + 1)) as i64 - range) as i16
+        };
+        Network {
+            hidden,
+            feature_weights: (0..FEATURES * hidden).map(|_| next(40)).collect(),
+            feature_bias: (0..hidden).map(|_| next(60)).collect(),
+            output_weights: (0..2 * hidden).map(|_| next(64)).collect(),
+            output_bias: next(500),
+        }
+    }
+
+    /// Serialise to the file format, the inverse of `Network::from_bytes`
+    fn to_bytes(net: &Network) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(net.hidden as u32).to_le_bytes());
+        for value in net
+            .feature_weights
+            .iter()
+            .chain(&net.feature_bias)
+            .chain(&net.output_weights)
+            .chain(std::iter::once(&net.output_bias))
+        {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_file_format() {
+        let net = test_network(16);
+        let bytes = to_bytes(&net);
+        let loaded = Network::from_bytes(&bytes).unwrap();
+        assert_eq!(to_bytes(&loaded), bytes);
+
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 2]).is_err());
+        assert!(Network::from_bytes(b"NOPE\x01\0\0\0\x10\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        // 24 is not a multiple of 16, so this also covers the scalar path
+        for hidden in [32, 24] {
+            let net = Arc::new(test_network(hidden));
+            let mut board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+            board.set_nnue(Some(Arc::clone(&net)));
+            let start = board.nnue.as_ref().unwrap().evaluate(board.side);
+
+            // En passant, castling both ways, and a promotion capture
+            for m in ["e5d6", "e8g8", "e1c1", "f8f2", "b7a8q"] {
+                let mov = find_legal_move(&mut board, Move::from_string(m).unwrap()).unwrap();
+                board.make_move(mov);
+                let fresh = NnueState::new(Arc::clone(&net), &board);
+                let state = board.nnue.as_ref().unwrap();
+                assert!(state.accumulator(WHITE) == fresh.accumulator(WHITE), "after {}", m);
+                assert!(state.accumulator(BLACK) == fresh.accumulator(BLACK), "after {}", m);
+            }
+
+            for _ in 0..5 {
+                board.unmake_move();
+            }
+            assert_eq!(board.nnue.as_ref().unwrap().evaluate(board.side), start);
+
+            // Unmaking past the position the accumulators were built from refreshes them
+            let mov = find_legal_move(&mut board, Move::from_string("a1a2").unwrap()).unwrap();
+            board.make_move(mov);
+            board.set_nnue(Some(Arc::clone(&net)));
+            board.unmake_move();
+            assert_eq!(board.nnue.as_ref().unwrap().evaluate(board.side), start);
+        }
+    }
+
+    #[test]
+    fn test_extreme_weights() {
+        // The largest network with every weight at the i16 limit overflows i32
+        // sums, and would evaluate far beyond the mate scores
+        for weight in [i16::MAX, i16::MIN] {
+            let hidden = MAX_HIDDEN;
+            let net = Network {
+                hidden,
+                feature_weights: vec![0; FEATURES * hidden],
+                feature_bias: vec![i16::MAX; hidden],
+                output_weights: vec![weight; 2 * hidden],
+                output_bias: weight,
+            };
+            let acc = vec![i16::MAX; hidden];
+            let expected = hidden as i64 * QA as i64 * weight as i64;
+            assert_eq!(crelu_dot(&acc, &net.output_weights[..hidden]), expected);
+            assert_eq!(crelu_dot_scalar(&acc, &net.output_weights[..hidden]), expected);
+
+            let net = Arc::new(Network::from_bytes(&to_bytes(&net)).unwrap());
+            let score = NnueState::new(net, &Board::starting_position()).evaluate(WHITE);
+            assert_eq!(score, weight.signum() as i32 * MAX_EVAL as i32);
+        }
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        let net = test_network(64);
+        let acc: Vec<i16> = (0..64).map(|i| (i * 37 % 400) as i16 - 100).collect();
+        assert_eq!(crelu_dot(&acc, &net.output_weights[..64]), crelu_dot_scalar(&acc, &net.output_weights[..64]));
+    }
+}
@@ -0,0 +1,511 @@
+//! Evaluation weights: the default set and the `EvalParams` structure the
+//! classical evaluation reads them from, saved and loaded as JSON
+
+use crate::board::{KING, KNIGHT};
+use crate::pawns::clear_pawn_tables;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::slice;
+use std::sync::{Arc, RwLock};
+
+/// Material values in centipawns for the middlegame and the endgame. The
+/// king is never captured, so it has no material value.
+const MG_PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+const EG_PIECE_VALUES: [i32; 6] = [120, 290, 310, 530, 950, 0];
+
+/// Piece-square tables for positional evaluation, for the middlegame and the
+/// endgame. Tables are laid out as seen from white's side, rank 8 on top.
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const KNIGHT_MG: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+const KNIGHT_EG: [i32; 64] = [
+    -40, -30, -20, -20, -20, -20, -30, -40,
+    -30, -15,   0,   0,   0,   0, -15, -30,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -20,   5,  15,  20,  20,  15,   5, -20,
+    -20,   5,  15,  20,  20,  15,   5, -20,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -30, -15,   0,   0,   0,   0, -15, -30,
+    -40, -30, -20, -20, -20, -20, -30, -40,
+];
+
+const BISHOP_MG: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+const BISHOP_EG: [i32; 64] = [
+    -15, -10, -10, -10, -10, -10, -10, -15,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -15, -10, -10, -10, -10, -10, -10, -15,
+];
+
+const ROOK_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+const ROOK_EG: [i32; 64] = [
+      5,   5,   5,   5,   5,   5,   5,   5,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const QUEEN_MG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+const QUEEN_EG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+     -5,   5,  10,  15,  15,  10,   5,  -5,
+     -5,   5,  10,  15,  15,  10,   5,  -5,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+/// In the endgame the king is an active piece and belongs in the centre
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// Mobility bonuses by the number of squares a piece can move to, not counting
+/// squares occupied by our own pieces or attacked by enemy pawns
+const KNIGHT_MOBILITY_MG: [i32; 9] = [-30, -20, -5, 0, 5, 10, 15, 20, 25];
+const KNIGHT_MOBILITY_EG: [i32; 9] = [-40, -25, -10, 0, 5, 10, 15, 18, 20];
+const BISHOP_MOBILITY_MG: [i32; 14] = [-25, -12, 0, 6, 12, 18, 22, 26, 29, 31, 33, 35, 37, 39];
+const BISHOP_MOBILITY_EG: [i32; 14] = [-35, -18, -5, 4, 10, 16, 22, 27, 31, 34, 36, 38, 40, 42];
+const ROOK_MOBILITY_MG: [i32; 15] = [-15, -8, -3, 0, 2, 4, 7, 10, 12, 14, 16, 18, 20, 21, 22];
+const ROOK_MOBILITY_EG: [i32; 15] = [-40, -20, -5, 5, 12, 18, 24, 30, 35, 40, 44, 47, 50, 52, 54];
+const QUEEN_MOBILITY_MG: [i32; 28] = [
+    -15, -10, -6, -3, 0, 2, 4, 6, 8, 9, 10, 11, 12, 13,
+    14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+];
+const QUEEN_MOBILITY_EG: [i32; 28] = [
+    -30, -20, -12, -6, -2, 2, 6, 10, 13, 16, 19, 22, 24, 26,
+    28, 30, 32, 34, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45,
+];
+
+/// Piece bonuses and penalties as (middlegame, endgame)
+const BISHOP_PAIR: (i32, i32) = (30, 50);
+const ROOK_OPEN_FILE: (i32, i32) = (25, 10);
+const ROOK_SEMI_OPEN_FILE: (i32, i32) = (12, 6);
+/// A rook on the seventh rank, when it cuts off the king or attacks pawns there
+const ROOK_ON_SEVENTH: (i32, i32) = (15, 30);
+/// A minor piece on the fourth to sixth rank, defended by a pawn, that no
+/// enemy pawn can ever attack
+const KNIGHT_OUTPOST: (i32, i32) = (25, 15);
+const BISHOP_OUTPOST: (i32, i32) = (15, 8);
+/// A rook shut in the corner by its own king, which can no longer castle
+const TRAPPED_ROOK: (i32, i32) = (-40, -10);
+/// A bishop on a7/h7 cut off by an enemy pawn on b6/g6
+const TRAPPED_BISHOP: (i32, i32) = (-80, -60);
+
+/// Bonus for our closest pawn in front of the king on each of its three files,
+/// by distance in ranks (0 = no pawn)
+const PAWN_SHIELD: [i32; 8] = [-15, 15, 8, 2, 0, 0, 0, 0];
+/// Penalty for the closest enemy pawn advancing on the king's files, by
+/// distance in ranks (0 = no pawn). A pawn right in front is usually blocked.
+const PAWN_STORM: [i32; 8] = [0, -5, -25, -15, -5, 0, 0, 0];
+/// Penalties for a file next to the king without our pawns, and without any pawns
+const SEMI_OPEN_KING_FILE: i32 = -10;
+const OPEN_KING_FILE: i32 = -20;
+
+/// Attack units for each enemy piece type per king zone square it attacks
+const ATTACK_WEIGHTS: [i32; 6] = [0, 2, 2, 3, 5, 0];
+/// Middlegame penalty by attack units, growing quadratically so that
+/// coordinated attacks count for much more than a lone attacker
+const KING_DANGER: [i32; 64] = king_danger_table();
+
+const fn king_danger_table() -> [i32; 64] {
+    let mut table = [0; 64];
+    let mut units = 0;
+    while units < 64 {
+        let danger = (units * units / 4) as i32;
+        table[units] = if danger < 500 { danger } else { 500 };
+        units += 1;
+    }
+    table
+}
+
+/// Bonuses by relative rank (rank 2 = index 1) for a passed pawn
+const PASSED_MG: [i32; 8] = [0, 5, 10, 15, 25, 40, 60, 0];
+const PASSED_EG: [i32; 8] = [0, 10, 15, 25, 45, 70, 110, 0];
+/// Extra endgame bonus when nothing stands on the passed pawn's way to promotion
+const PASSED_FREE_EG: [i32; 8] = [0, 0, 5, 10, 20, 35, 60, 0];
+/// Bonus by relative rank for a pawn defended by or side by side with another pawn
+const CONNECTED: [i32; 8] = [0, 5, 7, 10, 15, 25, 40, 0];
+
+/// Penalties as (middlegame, endgame)
+const DOUBLED: (i32, i32) = (-10, -20);
+const ISOLATED: (i32, i32) = (-10, -15);
+const BACKWARD: (i32, i32) = (-8, -10);
+
+/// Every weight of the classical evaluation. Files may list only some fields;
+/// the rest keep their defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalParams {
+    /// Material, by piece type
+    pub mg_piece_values: [i32; 6],
+    pub eg_piece_values: [i32; 6],
+
+    /// Piece-square tables, rank 8 first
+    #[serde(with = "fixed_array")]
+    pub pawn_mg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub pawn_eg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub knight_mg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub knight_eg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub bishop_mg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub bishop_eg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub rook_mg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub rook_eg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub queen_mg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub queen_eg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub king_mg: [i32; 64],
+    #[serde(with = "fixed_array")]
+    pub king_eg: [i32; 64],
+
+    /// Mobility by number of reachable squares
+    pub knight_mobility_mg: [i32; 9],
+    pub knight_mobility_eg: [i32; 9],
+    pub bishop_mobility_mg: [i32; 14],
+    pub bishop_mobility_eg: [i32; 14],
+    pub rook_mobility_mg: [i32; 15],
+    pub rook_mobility_eg: [i32; 15],
+    pub queen_mobility_mg: [i32; 28],
+    pub queen_mobility_eg: [i32; 28],
+
+    /// Piece placement as (middlegame, endgame)
+    pub bishop_pair: (i32, i32),
+    pub rook_open_file: (i32, i32),
+    pub rook_semi_open_file: (i32, i32),
+    pub rook_on_seventh: (i32, i32),
+    pub knight_outpost: (i32, i32),
+    pub bishop_outpost: (i32, i32),
+    pub trapped_rook: (i32, i32),
+    pub trapped_bishop: (i32, i32),
+
+    /// King safety (middlegame only)
+    pub pawn_shield: [i32; 8],
+    pub pawn_storm: [i32; 8],
+    pub semi_open_king_file: i32,
+    pub open_king_file: i32,
+    pub attack_weights: [i32; 6],
+    #[serde(with = "fixed_array")]
+    pub king_danger: [i32; 64],
+
+    /// Pawn structure; rank-indexed tables use the pawn's relative rank
+    pub passed_mg: [i32; 8],
+    pub passed_eg: [i32; 8],
+    pub passed_free_eg: [i32; 8],
+    pub connected: [i32; 8],
+    pub doubled: (i32, i32),
+    pub isolated: (i32, i32),
+    pub backward: (i32, i32),
+}
+
+/// Number of slices `EvalParams::values_mut` groups the tunable weights into
+pub const WEIGHT_GROUPS: usize = 54;
+
+/// The built-in weights
+pub const DEFAULT_PARAMS: EvalParams = EvalParams {
+    mg_piece_values: MG_PIECE_VALUES,
+    eg_piece_values: EG_PIECE_VALUES,
+    pawn_mg: PAWN_MG,
+    pawn_eg: PAWN_EG,
+    knight_mg: KNIGHT_MG,
+    knight_eg: KNIGHT_EG,
+    bishop_mg: BISHOP_MG,
+    bishop_eg: BISHOP_EG,
+    rook_mg: ROOK_MG,
+    rook_eg: ROOK_EG,
+    queen_mg: QUEEN_MG,
+    queen_eg: QUEEN_EG,
+    king_mg: KING_MG,
+    king_eg: KING_EG,
+    knight_mobility_mg: KNIGHT_MOBILITY_MG,
+    knight_mobility_eg: KNIGHT_MOBILITY_EG,
+    bishop_mobility_mg: BISHOP_MOBILITY_MG,
+    bishop_mobility_eg: BISHOP_MOBILITY_EG,
+    rook_mobility_mg: ROOK_MOBILITY_MG,
+    rook_mobility_eg: ROOK_MOBILITY_EG,
+    queen_mobility_mg: QUEEN_MOBILITY_MG,
+    queen_mobility_eg: QUEEN_MOBILITY_EG,
+    bishop_pair: BISHOP_PAIR,
+    rook_open_file: ROOK_OPEN_FILE,
+    rook_semi_open_file: ROOK_SEMI_OPEN_FILE,
+    rook_on_seventh: ROOK_ON_SEVENTH,
+    knight_outpost: KNIGHT_OUTPOST,
+    bishop_outpost: BISHOP_OUTPOST,
+    trapped_rook: TRAPPED_ROOK,
+    trapped_bishop: TRAPPED_BISHOP,
+    pawn_shield: PAWN_SHIELD,
+    pawn_storm: PAWN_STORM,
+    semi_open_king_file: SEMI_OPEN_KING_FILE,
+    open_king_file: OPEN_KING_FILE,
+    attack_weights: ATTACK_WEIGHTS,
+    king_danger: KING_DANGER,
+    passed_mg: PASSED_MG,
+    passed_eg: PASSED_EG,
+    passed_free_eg: PASSED_FREE_EG,
+    connected: CONNECTED,
+    doubled: DOUBLED,
+    isolated: ISOLATED,
+    backward: BACKWARD,
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+impl EvalParams {
+    /// Load weights from a JSON file
+    pub fn load(path: &str) -> Result<EvalParams, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid parameter file {}: {}", path, e))
+    }
+
+    /// Write all weights to a JSON file
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Every weight that affects the evaluation, grouped into slices in field
+    /// order, for tuning. Left out are the king's material value, the king
+    /// attack weights of pawns and kings, which are never counted, and pawn
+    /// weights for the first and last ranks, where no pawn can stand.
+    pub fn values_mut(&mut self) -> [&mut [i32]; WEIGHT_GROUPS] {
+        let king = KING as usize;
+        [
+            &mut self.mg_piece_values[..king],
+            &mut self.eg_piece_values[..king],
+            &mut self.pawn_mg[8..56],
+            &mut self.pawn_eg[8..56],
+            &mut self.knight_mg,
+            &mut self.knight_eg,
+            &mut self.bishop_mg,
+            &mut self.bishop_eg,
+            &mut self.rook_mg,
+            &mut self.rook_eg,
+            &mut self.queen_mg,
+            &mut self.queen_eg,
+            &mut self.king_mg,
+            &mut self.king_eg,
+            &mut self.knight_mobility_mg,
+            &mut self.knight_mobility_eg,
+            &mut self.bishop_mobility_mg,
+            &mut self.bishop_mobility_eg,
+            &mut self.rook_mobility_mg,
+            &mut self.rook_mobility_eg,
+            &mut self.queen_mobility_mg,
+            &mut self.queen_mobility_eg,
+            slice::from_mut(&mut self.bishop_pair.0),
+            slice::from_mut(&mut self.bishop_pair.1),
+            slice::from_mut(&mut self.rook_open_file.0),
+            slice::from_mut(&mut self.rook_open_file.1),
+            slice::from_mut(&mut self.rook_semi_open_file.0),
+            slice::from_mut(&mut self.rook_semi_open_file.1),
+            slice::from_mut(&mut self.rook_on_seventh.0),
+            slice::from_mut(&mut self.rook_on_seventh.1),
+            slice::from_mut(&mut self.knight_outpost.0),
+            slice::from_mut(&mut self.knight_outpost.1),
+            slice::from_mut(&mut self.bishop_outpost.0),
+            slice::from_mut(&mut self.bishop_outpost.1),
+            slice::from_mut(&mut self.trapped_rook.0),
+            slice::from_mut(&mut self.trapped_rook.1),
+            slice::from_mut(&mut self.trapped_bishop.0),
+            slice::from_mut(&mut self.trapped_bishop.1),
+            &mut self.pawn_shield,
+            &mut self.pawn_storm,
+            slice::from_mut(&mut self.semi_open_king_file),
+            slice::from_mut(&mut self.open_king_file),
+            &mut self.attack_weights[KNIGHT as usize..king],
+            &mut self.king_danger,
+            &mut self.passed_mg[1..7],
+            &mut self.passed_eg[1..7],
+            &mut self.passed_free_eg[1..7],
+            &mut self.connected[1..7],
+            slice::from_mut(&mut self.doubled.0),
+            slice::from_mut(&mut self.doubled.1),
+            slice::from_mut(&mut self.isolated.0),
+            slice::from_mut(&mut self.isolated.1),
+            slice::from_mut(&mut self.backward.0),
+            slice::from_mut(&mut self.backward.1),
+        ]
+    }
+}
+
+/// Weights set at runtime, used by searches started from then on; `None`
+/// means the built-in ones
+static ACTIVE: RwLock<Option<Arc<EvalParams>>> = RwLock::new(None);
+
+/// The weights in use
+pub fn active_params() -> Arc<EvalParams> {
+    ACTIVE.read().unwrap().clone().unwrap_or_else(|| Arc::new(DEFAULT_PARAMS))
+}
+
+/// Switch to other weights, or back to the built-in ones with `None`
+pub fn set_active_params(params: Option<EvalParams>) {
+    *ACTIVE.write().unwrap() = params.map(Arc::new);
+    clear_pawn_tables();
+}
+
+/// Serde support for arrays longer than serde's built-in 32 elements
+mod fixed_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(array: &[i32; N], serializer: S) -> Result<S::Ok, S::Error> {
+        array.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[i32; N], D::Error> {
+        let values = Vec::<i32>::deserialize(deserializer)?;
+        let len = values.len();
+        values.try_into().map_err(|_| D::Error::invalid_length(len, &format!("{} values", N).as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PAWN;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut params = EvalParams::default();
+        params.values_mut()[0][0] += 7;
+        params.king_danger[63] = -1;
+
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(serde_json::from_str::<EvalParams>(&json).unwrap(), params);
+
+        // Missing fields keep their defaults; wrongly sized tables are rejected
+        let partial: EvalParams = serde_json::from_str(r#"{"bishop_pair": [1, 2]}"#).unwrap();
+        assert_eq!(partial.bishop_pair, (1, 2));
+        assert_eq!(partial.pawn_mg, DEFAULT_PARAMS.pawn_mg);
+        assert!(serde_json::from_str::<EvalParams>(r#"{"pawn_mg": [1, 2]}"#).is_err());
+    }
+
+    #[test]
+    fn test_tunable_values() {
+        let mut params = EvalParams::default();
+        for group in params.values_mut() {
+            group.fill(0);
+        }
+        assert_eq!(params.mg_piece_values, [0, 0, 0, 0, 0, DEFAULT_PARAMS.mg_piece_values[KING as usize]]);
+        assert_eq!(params.attack_weights[PAWN as usize], DEFAULT_PARAMS.attack_weights[PAWN as usize]);
+        assert_eq!(params.attack_weights[KING as usize], DEFAULT_PARAMS.attack_weights[KING as usize]);
+        assert_eq!(params.pawn_mg[..8], DEFAULT_PARAMS.pawn_mg[..8]);
+        assert_eq!(params.pawn_eg[56..], DEFAULT_PARAMS.pawn_eg[56..]);
+        assert_eq!(params.passed_eg[7], DEFAULT_PARAMS.passed_eg[7]);
+        assert_eq!(params.pawn_mg[8..56], [0; 48]);
+        assert_eq!(params.king_danger, [0; 64]);
+        assert_eq!(params.backward, (0, 0));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("eval_params_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let params = EvalParams { doubled: (-3, -33), ..EvalParams::default() };
+
+        params.save(path).unwrap();
+        assert_eq!(EvalParams::load(path).unwrap(), params);
+        fs::remove_file(path).unwrap();
+        assert!(EvalParams::load(path).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! Pawn structure evaluation, cached in a per-thread pawn hash table
+
+use crate::board::*;
+use crate::movegen::pawn_attacks;
+use crate::params::EvalParams;
+use crate::utils::*;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Pawn table entries per thread (a power of two)
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+/// Cached pawn structure terms, from white's point of view
+#[derive(Clone, Copy, Default)]
+pub struct PawnEntry {
+    key: u64,
+    pub mg: i32,
+    pub eg: i32,
+    /// Passed pawns of each colour
+    pub passed: [u64; 2],
+}
+
+struct PawnTable {
+    /// Value of `GENERATION` the entries were computed under
+    generation: u32,
+    entries: Vec<PawnEntry>,
+}
+
+thread_local! {
+    static PAWN_TABLE: RefCell<PawnTable> = RefCell::new(PawnTable {
+        generation: 0,
+        entries: vec![PawnEntry::default(); PAWN_TABLE_SIZE],
+    });
+}
+
+/// Bumped when the evaluation weights change, so every thread drops the
+/// entries it computed with the old ones
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// Invalidate the pawn tables of all threads
+pub fn clear_pawn_tables() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Invalidate the calling thread's pawn table only
+pub fn clear_thread_pawn_table() {
+    PAWN_TABLE.with(|table| table.borrow_mut().entries.fill(PawnEntry::default()));
+}
+
+/// Whether pawn table entries computed with the `old` weights are still
+/// correct under `new`, i.e. the cached structure terms are unchanged
+pub fn same_cached_weights(old: &EvalParams, new: &EvalParams) -> bool {
+    old.passed_mg == new.passed_mg
+        && old.passed_eg == new.passed_eg
+        && old.connected == new.connected
+        && old.doubled == new.doubled
+        && old.isolated == new.isolated
+        && old.backward == new.backward
+}
+
+#[inline]
+pub const fn file_mask(file: u8) -> u64 {
+    FILE_A << file
+}
+
+/// Files either side of `file`
+#[inline]
+pub const fn adjacent_files(file: u8) -> u64 {
+    let mut mask = 0;
+    if file > 0 {
+        mask |= file_mask(file - 1);
+    }
+    if file < 7 {
+        mask |= file_mask(file + 1);
+    }
+    mask
+}
+
+/// Ranks strictly in front of `rank` from `color`'s point of view
+#[inline]
+pub const fn ranks_ahead(rank: u8, color: u8) -> u64 {
+    if color == WHITE {
+        if rank >= 7 { 0 } else { !0u64 << ((rank + 1) * 8) }
+    } else if rank == 0 {
+        0
+    } else {
+        !0u64 >> ((8 - rank) * 8)
+    }
+}
+
+/// Squares attacked by a set of `color`'s pawns
+#[inline]
+pub const fn pawn_attack_set(pawns: u64, color: u8) -> u64 {
+    let west = pawns & !file_mask(0);
+    let east = pawns & !file_mask(7);
+    if color == WHITE {
+        (west << 7) | (east << 9)
+    } else {
+        (west >> 9) | (east >> 7)
+    }
+}
+
+/// Squares in front of a pawn on its own file, up to the promotion square
+#[inline]
+pub fn front_span(sq: u8, color: u8) -> u64 {
+    file_mask(file_of(sq)) & ranks_ahead(rank_of(sq), color)
+}
+
+/// Rank counted from `color`'s side of the board (0 = back rank)
+#[inline]
+pub const fn relative_rank(sq: u8, color: u8) -> usize {
+    if color == WHITE {
+        rank_of(sq) as usize
+    } else {
+        7 - rank_of(sq) as usize
+    }
+}
+
+/// Structure terms for `color`'s pawns as (middlegame, endgame, passed pawns),
+/// from `color`'s point of view
+fn color_structure(board: &Board, color: u8, params: &EvalParams) -> (i32, i32, u64) {
+    let enemy = color ^ 1;
+    let ours = board.pieces[color as usize][PAWN as usize];
+    let theirs = board.pieces[enemy as usize][PAWN as usize];
+    let (mut mg, mut eg, mut passed) = (0, 0, 0);
+
+    let mut pawns = ours;
+    while pawns != 0 {
+        let sq = pop_lsb(&mut pawns);
+        let file = file_of(sq);
+        let rank = relative_rank(sq, color);
+        let ahead = ranks_ahead(rank_of(sq), color);
+        let neighbours = adjacent_files(file);
+
+        // Passed: no enemy pawn in front on this or an adjacent file
+        if theirs & (file_mask(file) | neighbours) & ahead == 0 {
+            passed |= bit_at(sq);
+            mg += params.passed_mg[rank];
+            eg += params.passed_eg[rank];
+        }
+
+        // Doubled: another of our pawns in front on the same file
+        if ours & file_mask(file) & ahead != 0 {
+            mg += params.doubled.0;
+            eg += params.doubled.1;
+        }
+
+        if ours & neighbours == 0 {
+            mg += params.isolated.0;
+            eg += params.isolated.1;
+        } else {
+            let supporters = pawn_attacks(sq, enemy) & ours;
+            let phalanx = ours & neighbours & (0xFFu64 << (rank_of(sq) * 8));
+            if supporters | phalanx != 0 {
+                mg += params.connected[rank];
+                eg += params.connected[rank];
+            } else if ours & neighbours & !ahead == 0 {
+                // Backward: every neighbour has advanced past it, and the
+                // square in front is controlled by an enemy pawn
+                let stop = if color == WHITE { sq + 8 } else { sq - 8 };
+                if pawn_attacks(stop, color) & theirs != 0 {
+                    mg += params.backward.0;
+                    eg += params.backward.1;
+                }
+            }
+        }
+    }
+
+    (mg, eg, passed)
+}
+
+/// Pawn structure terms from scratch
+fn evaluate_structure(board: &Board, params: &EvalParams) -> PawnEntry {
+    let mut entry = PawnEntry { key: board.pawn_hash, ..PawnEntry::default() };
+
+    for color in [WHITE, BLACK] {
+        let sign = if color == WHITE { 1 } else { -1 };
+        let (mg, eg, passed) = color_structure(board, color, params);
+        entry.mg += sign * mg;
+        entry.eg += sign * eg;
+        entry.passed[color as usize] = passed;
+    }
+
+    entry
+}
+
+/// Endgame bonus for those of `color`'s passed pawns with nothing in front of them
+fn free_passer_bonus(board: &Board, mut passed: u64, color: u8, params: &EvalParams) -> i32 {
+    let occupied = board.all_occupancy();
+    let mut bonus = 0;
+    while passed != 0 {
+        let sq = pop_lsb(&mut passed);
+        if front_span(sq, color) & occupied == 0 {
+            bonus += params.passed_free_eg[relative_rank(sq, color)];
+        }
+    }
+    bonus
+}
+
+/// Pawn structure terms for the position, from the calling thread's pawn table
+pub fn pawn_structure(board: &Board, params: &EvalParams) -> PawnEntry {
+    PAWN_TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        let generation = GENERATION.load(Ordering::Relaxed);
+        if table.generation != generation {
+            table.entries.fill(PawnEntry::default());
+            table.generation = generation;
+        }
+        let slot = &mut table.entries[board.pawn_hash as usize & (PAWN_TABLE_SIZE - 1)];
+        if slot.key != board.pawn_hash {
+            *slot = evaluate_structure(board, params);
+        }
+        *slot
+    })
+}
+
+/// Pawn structure score as (middlegame, endgame) from white's point of view:
+/// the cached structure terms plus a bonus for passed pawns with a clear path,
+/// which depends on the other pieces and so is not cached
+pub fn evaluate_pawns(board: &Board, params: &EvalParams) -> (i32, i32) {
+    let entry = pawn_structure(board, params);
+    let eg = entry.eg + free_passer_bonus(board, entry.passed[WHITE as usize], WHITE, params)
+        - free_passer_bonus(board, entry.passed[BLACK as usize], BLACK, params);
+    (entry.mg, eg)
+}
+
+/// Pawn structure score of one side as (middlegame, endgame) from its own point
+/// of view, bypassing the pawn table. Used by the evaluation trace.
+pub fn pawn_terms(board: &Board, color: u8, params: &EvalParams) -> (i32, i32) {
+    let (mg, eg, passed) = color_structure(board, color, params);
+    (mg, eg + free_passer_bonus(board, passed, color, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::DEFAULT_PARAMS;
+
+    fn structure_of(fen: &str) -> PawnEntry {
+        evaluate_structure(&Board::from_fen(fen).unwrap(), &DEFAULT_PARAMS)
+    }
+
+    #[test]
+    fn test_passed_pawns() {
+        let entry = structure_of("4k3/8/8/1P6/8/8/6p1/4K3 w - - 0 1");
+        assert_eq!(entry.passed[WHITE as usize], bit_at(33));
+        assert_eq!(entry.passed[BLACK as usize], bit_at(14));
+
+        // A pawn on an adjacent file in front stops it being passed
+        let entry = structure_of("4k3/2p5/8/1P6/8/8/8/4K3 w - - 0 1");
+        assert_eq!(entry.passed[WHITE as usize], 0);
+
+        // The path bonus depends on blockers
+        let free = evaluate_pawns(&Board::from_fen("4k3/8/8/1P6/8/8/8/4K3 w - - 0 1").unwrap(), &DEFAULT_PARAMS);
+        let blocked = evaluate_pawns(&Board::from_fen("4k3/8/1n6/1P6/8/8/8/4K3 w - - 0 1").unwrap(), &DEFAULT_PARAMS);
+        assert!(free.1 > blocked.1);
+    }
+
+    #[test]
+    fn test_structure_penalties() {
+        let healthy = structure_of("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1");
+        let doubled = structure_of("4k3/8/8/8/8/P7/P1P5/4K3 w - - 0 1");
+        let isolated = structure_of("4k3/8/8/8/8/8/P1P5/4K3 w - - 0 1");
+        assert!(healthy.mg > doubled.mg);
+        assert!(healthy.eg > isolated.eg);
+
+        // d3 is backward: c4 and e4 have advanced and the d4 square is covered by black's c5 pawn
+        let backward = structure_of("4k3/8/8/2p5/2P1P3/3P4/8/4K3 w - - 0 1");
+        let supported = structure_of("4k3/8/8/2p5/2PP4/4P3/8/4K3 w - - 0 1");
+        assert!(supported.mg > backward.mg);
+
+        // Symmetric structures cancel out
+        assert_eq!(structure_of("4k3/pp3ppp/8/8/8/8/PP3PPP/4K3 w - - 0 1").mg, 0);
+    }
+}
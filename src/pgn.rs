@@ -0,0 +1,468 @@
+//! PGN (Portable Game Notation) import and export
+
+use crate::board::*;
+use crate::movegen::*;
+use crate::san::*;
+use crate::utils::*;
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Line length limit for exported movetext
+const MAX_LINE_LEN: usize = 79;
+
+/// A single game: tag pairs in file order plus the main line
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<Move>,
+    pub result: String,
+}
+
+impl PgnGame {
+    /// Create a game with the Seven Tag Roster filled in
+    pub fn new(white: &str, black: &str, date: &str, result: &str) -> Self {
+        let tags = [
+            ("Event", "Casual game"),
+            ("Site", "Chess Engine 2.0"),
+            ("Date", date),
+            ("Round", "-"),
+            ("White", white),
+            ("Black", black),
+            ("Result", result),
+        ];
+
+        PgnGame {
+            tags: tags.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+            moves: Vec::new(),
+            result: result.to_string(),
+        }
+    }
+
+    /// Get a tag value by name
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Set a tag value, appending it if not present
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(k, _)| k == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Position the game starts from (the FEN tag, or the standard start)
+    pub fn starting_board(&self) -> Result<Board, String> {
+        Board::from_fen(self.tag("FEN").unwrap_or(START_FEN))
+    }
+
+    /// Replay the main line, returning the final position
+    pub fn replay(&self) -> Result<Board, String> {
+        let mut board = self.starting_board()?;
+        for (ply, &mov) in self.moves.iter().enumerate() {
+            if find_legal_move(&mut board, mov) != Some(mov) {
+                return Err(format!("Illegal move {} at ply {}", mov.to_string(), ply + 1));
+            }
+            board.make_move(mov);
+        }
+        Ok(board)
+    }
+
+    /// Export the game as PGN text
+    pub fn to_pgn(&self) -> Result<String, String> {
+        let mut out = String::new();
+        for (name, value) in &self.tags {
+            out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+        out.push('\n');
+
+        let mut board = self.starting_board()?;
+        let mut tokens = Vec::with_capacity(self.moves.len() * 2);
+
+        for (ply, &mov) in self.moves.iter().enumerate() {
+            if board.side == WHITE {
+                tokens.push(format!("{}.", board.fullmove));
+            } else if ply == 0 {
+                tokens.push(format!("{}...", board.fullmove));
+            }
+
+            if find_legal_move(&mut board, mov) != Some(mov) {
+                return Err(format!("Illegal move {} at ply {}", mov.to_string(), ply + 1));
+            }
+            tokens.push(move_to_san(&mut board, mov));
+            board.make_move(mov);
+        }
+        tokens.push(self.result.clone());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+                out.push('\n');
+                line_len = 0;
+            }
+            if line_len > 0 {
+                out.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            out.push_str(&token);
+        }
+        out.push('\n');
+
+        Ok(out)
+    }
+}
+
+/// Build a PGN game from a move list played from `start`
+pub fn game_from_moves(
+    start: &Board,
+    moves: &[Move],
+    white: &str,
+    black: &str,
+    timestamp: &str,
+    result: &str,
+) -> PgnGame {
+    let mut game = PgnGame::new(white, black, &pgn_date(timestamp), result);
+    let fen = start.to_fen();
+    if fen != START_FEN {
+        game.set_tag("SetUp", "1");
+        game.set_tag("FEN", &fen);
+    }
+    game.moves = moves.to_vec();
+    game
+}
+
+/// Convert a Unix timestamp (seconds, as stored in saves) to a PGN date "YYYY.MM.DD"
+pub fn pgn_date(timestamp: &str) -> String {
+    let secs: i64 = match timestamp.parse() {
+        Ok(s) => s,
+        Err(_) => return "????.??.??".to_string(),
+    };
+
+    // Days since 1970-01-01 to civil date (Howard Hinnant's algorithm)
+    let z = secs.div_euclid(86400) + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Lexical PGN tokens
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Symbol(String),
+    Result(String),
+    VariationStart,
+    VariationEnd,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line_start = true;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // "%" at the start of a line escapes the whole line
+        if line_start && c == '%' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        line_start = c == '\n';
+
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '{' => {
+                while i < chars.len() && chars[i] != '}' {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err("Unterminated comment".to_string());
+                }
+                i += 1;
+            }
+            ';' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '(' => {
+                tokens.push(Token::VariationStart);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::VariationEnd);
+                i += 1;
+            }
+            '$' => {
+                // Numeric annotation glyph
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            '[' => {
+                let (tag, end) = parse_tag(&chars, i + 1)?;
+                tokens.push(tag);
+                i = end;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !"{}()[];$".contains(chars[i])
+                {
+                    i += 1;
+                }
+                let symbol: String = chars[start..i].iter().collect();
+                match symbol.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push(Token::Result(symbol)),
+                    _ => tokens.push(Token::Symbol(symbol)),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a tag pair `Name "value"]` starting just after its `[`. The value is
+/// a quoted string with backslash escapes, so it may contain `]`. Returns the tag and
+/// the index after the closing bracket.
+fn parse_tag(chars: &[char], start: usize) -> Result<(Token, usize), String> {
+    let skip_spaces = |mut i: usize| {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    };
+
+    let name_start = skip_spaces(start);
+    let mut i = name_start;
+    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+        i += 1;
+    }
+    let name: String = chars[name_start..i].iter().collect();
+    if name.is_empty() {
+        return Err("Malformed tag pair".to_string());
+    }
+
+    i = skip_spaces(i);
+    if chars.get(i) != Some(&'"') {
+        return Err(format!("Malformed tag value for {}", name));
+    }
+    i += 1;
+
+    let mut value = String::new();
+    loop {
+        match chars.get(i) {
+            None => return Err(format!("Unterminated tag value for {}", name)),
+            Some('"') => break,
+            Some('\\') => {
+                i += 1;
+                value.push(*chars.get(i).ok_or_else(|| format!("Unterminated tag value for {}", name))?);
+            }
+            Some(&ch) => value.push(ch),
+        }
+        i += 1;
+    }
+
+    i = skip_spaces(i + 1);
+    if chars.get(i) != Some(&']') {
+        return Err(format!("Unterminated tag pair {}", name));
+    }
+    Ok((Token::Tag(name, value), i + 1))
+}
+
+/// Strip a move number prefix such as "12." or "12..." from a symbol
+fn strip_move_number(symbol: &str) -> &str {
+    let digits = symbol.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && symbol[digits..].starts_with('.') {
+        symbol[digits..].trim_start_matches('.')
+    } else {
+        symbol
+    }
+}
+
+/// Parse every game in a PGN file. Comments, NAGs and variations are skipped;
+/// only the main line is kept.
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, String> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut board: Option<Board> = None;
+    let mut moves = Vec::new();
+    let mut depth = 0;
+    // Movetext (moves, move numbers or variations) seen since the tags
+    let mut in_movetext = false;
+
+    let finish = |tags: &mut Vec<(String, String)>, moves: &mut Vec<Move>, result: String| {
+        let mut game = PgnGame {
+            tags: std::mem::take(tags),
+            moves: std::mem::take(moves),
+            result: result.clone(),
+        };
+        game.set_tag("Result", &result);
+        game
+    };
+
+    for token in tokenize(text)? {
+        match token {
+            Token::Tag(name, value) => {
+                // A tag after movetext, or a second tag of the same name, starts
+                // the next game: this one ended without a result token
+                if in_movetext || tags.iter().any(|(k, _)| *k == name) {
+                    games.push(finish(&mut tags, &mut moves, "*".to_string()));
+                    board = None;
+                    in_movetext = false;
+                    depth = 0;
+                }
+                tags.push((name, value));
+            }
+            Token::VariationStart => {
+                depth += 1;
+                in_movetext = true;
+            }
+            Token::VariationEnd => {
+                if depth == 0 {
+                    return Err(format!("Game {}: unbalanced ')'", games.len() + 1));
+                }
+                depth -= 1;
+            }
+            Token::Result(result) if depth == 0 => {
+                games.push(finish(&mut tags, &mut moves, result));
+                board = None;
+                in_movetext = false;
+            }
+            Token::Symbol(symbol) if depth == 0 => {
+                in_movetext = true;
+                let san = strip_move_number(&symbol);
+                if san.is_empty() {
+                    continue;
+                }
+
+                if board.is_none() {
+                    let fen = tags.iter().find(|(k, _)| k == "FEN").map(|(_, v)| v.as_str());
+                    board = Some(
+                        Board::from_fen(fen.unwrap_or(START_FEN))
+                            .map_err(|e| format!("Game {}: bad FEN tag: {}", games.len() + 1, e))?,
+                    );
+                }
+                let current = board.as_mut().unwrap();
+
+                let mov = parse_san(current, san).ok_or_else(|| {
+                    format!("Game {}: illegal move '{}' at ply {}", games.len() + 1, san, moves.len() + 1)
+                })?;
+                current.make_move(mov);
+                moves.push(mov);
+            }
+            // Moves and results inside variations are skipped
+            _ => {}
+        }
+    }
+
+    if !moves.is_empty() || !tags.is_empty() {
+        games.push(finish(&mut tags, &mut moves, "*".to_string()));
+    }
+
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let mut board = Board::starting_position();
+        let mut moves = Vec::new();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "O-O"] {
+            let mov = parse_san(&mut board, san).unwrap();
+            board.make_move(mov);
+            moves.push(mov);
+        }
+
+        let start = Board::starting_position();
+        let game = game_from_moves(&start, &moves, "alice", "bob", "0", "*");
+        let text = game.to_pgn().unwrap();
+        assert!(text.contains("[White \"alice\"]"));
+        assert!(text.contains("[Date \"1970.01.01\"]"));
+        assert!(text.contains("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. O-O *"));
+
+        let parsed = parse_pgn(&text).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].moves, moves);
+        assert_eq!(parsed[0].tag("Black"), Some("bob"));
+    }
+
+    #[test]
+    fn test_pgn_comments_nags_variations() {
+        let text = r#"
+[Event "Test"]
+[White "A \"quoted\" name"]
+
+1. e4 {best by test} e5 $1 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6
+; rest of line comment
+3. Bc4!? Bc5 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+
+1... Kd7 2. e4 *
+"#;
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("A \"quoted\" name"));
+        assert_eq!(games[0].moves.len(), 6);
+        assert_eq!(games[0].result, "1-0");
+        assert_eq!(games[1].moves.len(), 2);
+        assert_eq!(games[1].replay().unwrap().to_fen(), "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 2");
+    }
+
+    #[test]
+    fn test_pgn_tag_values_and_game_boundaries() {
+        // A bracket inside a quoted value doesn't end the tag
+        let games = parse_pgn("[Event \"Open [A]\"]\n[Site \"a \\\"b\\\" ]c\"]\n\n1. e4 *").unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("Event"), Some("Open [A]"));
+        assert_eq!(games[0].tag("Site"), Some("a \"b\" ]c"));
+        assert_eq!(games[0].moves.len(), 1);
+
+        // Tag sections with no movetext or result between them are separate games
+        let text = "[Event \"One\"]\n[White \"a\"]\n\n[Event \"Two\"]\n[White \"b\"]\n\n1. d4 d5\n[Event \"Three\"]\n";
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!((games[0].tag("White"), games[0].moves.len()), (Some("a"), 0));
+        assert_eq!((games[1].tag("White"), games[1].moves.len()), (Some("b"), 2));
+        assert_eq!(games[2].tag("Event"), Some("Three"));
+
+        assert!(parse_pgn("[Event \"unterminated]\n1. e4 *").is_err());
+    }
+
+    #[test]
+    fn test_pgn_illegal_move() {
+        let err = parse_pgn("1. e4 e5 2. Ke3 *").unwrap_err();
+        assert!(err.contains("ply 3"));
+    }
+
+    #[test]
+    fn test_pgn_date() {
+        assert_eq!(pgn_date("1761893512"), "2025.10.31");
+        assert_eq!(pgn_date("bogus"), "????.??.??");
+    }
+}
@@ -0,0 +1,59 @@
+//! Elo rating updates and per-user rating history
+
+use serde::{Deserialize, Serialize};
+
+/// One rated game in a user's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatingChange {
+    pub timestamp: String,
+    pub opponent: String,
+    pub opponent_rating: u32,
+    /// 1.0 win, 0.5 draw, 0.0 loss
+    pub score: f64,
+    pub before: u32,
+    pub after: u32,
+}
+
+/// Expected score against `opponent` under the Elo model
+pub fn expected_score(rating: u32, opponent: u32) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent as f64 - rating as f64) / 400.0))
+}
+
+/// Development coefficient: new players move faster until their rating settles
+pub fn k_factor(games_played: u32) -> f64 {
+    if games_played < 30 {
+        40.0
+    } else {
+        20.0
+    }
+}
+
+/// Rating after scoring `score` against `opponent`, given the games played before this one
+pub fn updated_rating(rating: u32, opponent: u32, score: f64, games_played: u32) -> u32 {
+    let change = k_factor(games_played) * (score - expected_score(rating, opponent));
+    (rating as f64 + change).round().max(100.0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_score() {
+        assert!((expected_score(1500, 1500) - 0.5).abs() < 1e-9);
+        assert!((expected_score(1600, 1200) - 0.909).abs() < 0.001);
+        assert!((expected_score(1200, 1600) + expected_score(1600, 1200) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_updated_rating() {
+        // Even game: +/- K/2
+        assert_eq!(updated_rating(1200, 1200, 1.0, 0), 1220);
+        assert_eq!(updated_rating(1200, 1200, 0.0, 0), 1180);
+        assert_eq!(updated_rating(1200, 1200, 0.5, 50), 1200);
+
+        // Established players move less; draws against stronger players gain
+        assert_eq!(updated_rating(1500, 1500, 1.0, 30), 1510);
+        assert!(updated_rating(1400, 1800, 0.5, 30) > 1400);
+    }
+}
@@ -0,0 +1,241 @@
+//! Standard Algebraic Notation (SAN) output and parsing, e.g. "Nf3", "exd5", "O-O", "e8=Q+"
+
+use crate::board::*;
+use crate::movegen::*;
+use crate::utils::*;
+
+/// Uppercase SAN letter for a piece type
+const fn piece_letter(piece: u8) -> char {
+    match piece {
+        KNIGHT => 'N',
+        BISHOP => 'B',
+        ROOK => 'R',
+        QUEEN => 'Q',
+        KING => 'K',
+        _ => 'P',
+    }
+}
+
+/// Piece type for an uppercase SAN letter
+fn letter_piece(ch: char) -> Option<u8> {
+    match ch {
+        'N' => Some(KNIGHT),
+        'B' => Some(BISHOP),
+        'R' => Some(ROOK),
+        'Q' => Some(QUEEN),
+        'K' => Some(KING),
+        _ => None,
+    }
+}
+
+/// Convert a legal move to SAN, including the check (+) or checkmate (#) suffix
+pub fn move_to_san(board: &mut Board, mov: Move) -> String {
+    let mut san = String::new();
+    let (piece, _) = match board.piece_at(mov.from()) {
+        Some(p) => p,
+        None => return mov.to_string(),
+    };
+
+    if mov.is_castle() {
+        san.push_str(if file_of(mov.to()) > file_of(mov.from()) { "O-O" } else { "O-O-O" });
+    } else {
+        let is_capture = board.piece_at(mov.to()).is_some() || mov.is_en_passant();
+
+        if piece == PAWN {
+            if is_capture {
+                san.push(file_char(file_of(mov.from())));
+            }
+        } else {
+            san.push(piece_letter(piece));
+            san.push_str(&disambiguation(board, mov, piece));
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(mov.to()));
+
+        if mov.is_promotion() {
+            san.push('=');
+            san.push(piece_letter(mov.promotion()));
+        }
+    }
+
+    board.make_move(mov);
+    if in_check(board) {
+        san.push(if generate_legal_moves(board).is_empty() { '#' } else { '+' });
+    }
+    board.unmake_move();
+
+    san
+}
+
+/// File and/or rank needed to tell `mov` apart from other legal moves of the same
+/// piece type to the same square
+fn disambiguation(board: &mut Board, mov: Move, piece: u8) -> String {
+    let from = mov.from();
+    let rivals: Vec<u8> = generate_legal_moves(board)
+        .into_iter()
+        .filter(|m| m.to() == mov.to() && m.from() != from)
+        .filter(|m| board.piece_at(m.from()).map(|(p, _)| p) == Some(piece))
+        .map(|m| m.from())
+        .collect();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|&sq| file_of(sq) != file_of(from)) {
+        file_char(file_of(from)).to_string()
+    } else if rivals.iter().all(|&sq| rank_of(sq) != rank_of(from)) {
+        rank_char(rank_of(from)).to_string()
+    } else {
+        square_name(from)
+    }
+}
+
+/// Convert a sequence of legal moves (e.g. a PV) to SAN, playing them out and back
+pub fn line_to_san(board: &mut Board, moves: &[Move]) -> Vec<String> {
+    let mut sans = Vec::with_capacity(moves.len());
+    for &mov in moves {
+        sans.push(move_to_san(board, mov));
+        board.make_move(mov);
+    }
+    for _ in moves {
+        board.unmake_move();
+    }
+    sans
+}
+
+/// Parse a SAN move against the current position. Check/annotation suffixes
+/// (+, #, !, ?) are ignored, "0-0" is accepted for "O-O" and the '=' before a
+/// promotion piece is optional. Returns None if the move is illegal or ambiguous.
+pub fn parse_san(board: &mut Board, san: &str) -> Option<Move> {
+    let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = generate_legal_moves(board);
+
+    match san {
+        "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+            let kingside = san.len() == 3;
+            return legal_moves
+                .into_iter()
+                .find(|m| m.is_castle() && (file_of(m.to()) > file_of(m.from())) == kingside);
+        }
+        _ => {}
+    }
+
+    let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != '-' && c != ':').collect();
+
+    // Promotion piece, with or without '='
+    let mut promotion = None;
+    if let Some(&last) = chars.last() {
+        if let Some(p) = letter_piece(last.to_ascii_uppercase()).filter(|&p| p != KING) {
+            let before = chars.len().checked_sub(2).map(|i| chars[i]);
+            if matches!(before, Some('=') | Some('1') | Some('8')) {
+                promotion = Some(p);
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+    }
+
+    let piece = match chars.first().and_then(|&c| letter_piece(c)) {
+        Some(p) => {
+            chars.remove(0);
+            p
+        }
+        None => PAWN,
+    };
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let dest: String = chars[chars.len() - 2..].iter().collect();
+    let to = parse_square(&dest)?;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for &c in &chars[..chars.len() - 2] {
+        match c {
+            'a'..='h' => from_file = Some(c as u8 - b'a'),
+            '1'..='8' => from_rank = Some(c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    let mut candidates = legal_moves.into_iter().filter(|m| {
+        m.to() == to
+            && !m.is_castle()
+            && board.piece_at(m.from()).map(|(p, _)| p) == Some(piece)
+            && from_file.is_none_or(|f| file_of(m.from()) == f)
+            && from_rank.is_none_or(|r| rank_of(m.from()) == r)
+            && match promotion {
+                Some(p) => m.is_promotion() && m.promotion() == p,
+                None => !m.is_promotion(),
+            }
+    });
+
+    let found = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+    Some(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san_of(fen: &str, coord: &str) -> String {
+        let mut board = Board::from_fen(fen).unwrap();
+        let mov = find_legal_move(&mut board, Move::from_string(coord).unwrap()).unwrap();
+        move_to_san(&mut board, mov)
+    }
+
+    #[test]
+    fn test_san_output() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san_of(start, "g1f3"), "Nf3");
+        assert_eq!(san_of(start, "e2e4"), "e4");
+
+        // Castling, en-passant and promotion with check
+        assert_eq!(san_of("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"), "O-O");
+        assert_eq!(san_of("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"), "O-O-O");
+        assert_eq!(san_of("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+        assert_eq!(san_of("8/P3k3/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), "a8=Q");
+        assert_eq!(san_of("3k4/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8r"), "a8=R+");
+
+        // Checkmate
+        assert_eq!(san_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn test_san_disambiguation() {
+        // Knights on b1 and f1 can both reach d2: file disambiguation
+        assert_eq!(san_of("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        // Rooks on a1 and a5 share a file: rank disambiguation
+        assert_eq!(san_of("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+        // Queens on a1, a3 and c1 all reach b2: full square needed for a1
+        assert_eq!(san_of("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2"), "Qa1b2");
+    }
+
+    #[test]
+    fn test_san_parsing() {
+        let mut board = Board::starting_position();
+        assert_eq!(parse_san(&mut board, "Nf3"), Some(Move::new(6, 21)));
+        assert_eq!(parse_san(&mut board, "e4!?"), Some(Move::new(12, 28)));
+        assert_eq!(parse_san(&mut board, "Ke2"), None);
+        assert_eq!(parse_san(&mut board, "Nd2"), None);
+
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(parse_san(&mut board, "0-0"), Some(Move::with_flags(4, 6, 1)));
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&mut board, "Nd2"), None); // ambiguous
+        assert_eq!(parse_san(&mut board, "Nfd2"), Some(Move::new(5, 11)));
+
+        let mut board = Board::from_fen("8/P3k3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&mut board, "a8=N"), Some(Move::with_promotion(48, 56, 1)));
+        assert_eq!(parse_san(&mut board, "a8Q"), Some(Move::with_promotion(48, 56, 4)));
+    }
+}
@@ -3,20 +3,94 @@
 use crate::board::*;
 use crate::eval::*;
 use crate::movegen::*;
+use crate::nnue::Network;
+use crate::params::{active_params, EvalParams};
+use crate::san::line_to_san;
+use crate::see::*;
+use crate::timeman::TimeManager;
 use crate::tt::*;
 use crate::utils::*;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::thread;
 
 const MAX_DEPTH: u8 = 64;
 const MATE_SCORE: i32 = 30000;
 const MAX_PLY: usize = 128;
 
-/// Search limits
+/// Null-move pruning is tried from this depth on
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+/// Base depth reduction for the null-move search, growing by one every 6 plies
+const NULL_MOVE_REDUCTION: u8 = 2;
+
+/// Late move reductions apply from this depth on
+const LMR_MIN_DEPTH: u8 = 3;
+/// Moves searched at full depth before late move reductions start
+const LMR_MIN_MOVES: usize = 3;
+/// History score above which a quiet move is reduced one ply less
+const LMR_HISTORY_THRESHOLD: i32 = 1000;
+
+/// Aspiration windows are used from this depth on
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+/// Initial half-width of the aspiration window, doubled after every failure
+const ASPIRATION_WINDOW: i32 = 50;
+
+/// Search limits. Times are in milliseconds.
+#[derive(Clone, Copy, Default)]
 pub struct SearchLimits {
     pub depth: Option<u8>,
     pub movetime: Option<u128>,
     pub nodes: Option<u64>,
+    pub wtime: Option<u128>,
+    pub btime: Option<u128>,
+    pub winc: u128,
+    pub binc: u128,
+    pub movestogo: Option<u128>,
+}
+
+impl SearchLimits {
+    /// Parse UCI-style `go` arguments: depth, movetime, nodes, wtime, btime,
+    /// winc, binc and movestogo. Other tokens are ignored.
+    pub fn parse(args: &[&str]) -> Self {
+        let mut limits = SearchLimits::default();
+        for pair in args.windows(2) {
+            let value = pair[1];
+            match pair[0] {
+                "depth" => limits.depth = value.parse().ok(),
+                "movetime" => limits.movetime = value.parse().ok(),
+                "nodes" => limits.nodes = value.parse().ok(),
+                "wtime" => limits.wtime = value.parse().ok(),
+                "btime" => limits.btime = value.parse().ok(),
+                "winc" => limits.winc = value.parse().unwrap_or(0),
+                "binc" => limits.binc = value.parse().unwrap_or(0),
+                "movestogo" => limits.movestogo = value.parse().ok(),
+                _ => {}
+            }
+        }
+        limits
+    }
+
+    /// Whether any limit is set; without one the search runs until stopped
+    pub fn is_limited(&self) -> bool {
+        self.depth.is_some()
+            || self.movetime.is_some()
+            || self.nodes.is_some()
+            || self.wtime.is_some()
+            || self.btime.is_some()
+    }
+}
+
+/// Protocol used to report search progress after each iteration
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InfoFormat {
+    /// UCI `info ...` lines
+    Uci,
+    /// UCI-style lines with the PV in SAN, for the interactive REPL
+    Repl,
+    /// CECP thinking output: `ply score time nodes pv`
+    Cecp,
+    /// No output
+    Silent,
 }
 
 /// Search statistics
@@ -25,6 +99,8 @@ pub struct SearchInfo {
     pub depth: u8,
     pub seldepth: u8,
     pub score: i32,
+    /// Whether `score` is exact or only a bound from a failed aspiration search
+    pub bound: Bound,
     pub pv: Vec<Move>,
     pub time_ms: u128,
 }
@@ -54,10 +130,16 @@ impl MoveScorer {
             return 10_000_000;
         }
 
-        // MVV-LVA for captures
+        // Captures by MVV-LVA, with those that lose material in the exchange
+        // ordered after killers
         if let Some((captured, _)) = board.piece_at(mov.to()) {
             if let Some((attacker, _)) = board.piece_at(mov.from()) {
-                return 1_000_000 + (captured as i32) * 100 - (attacker as i32);
+                let mvv_lva = (captured as i32) * 100 - (attacker as i32);
+                return if see_value(captured) < see_value(attacker) && see(board, mov) < 0 {
+                    600_000 + mvv_lva
+                } else {
+                    1_000_000 + mvv_lva
+                };
             }
         }
 
@@ -82,6 +164,14 @@ impl MoveScorer {
         self.history[board.side as usize][from][to]
     }
 
+    fn is_killer(&self, mov: Move, ply: usize) -> bool {
+        ply < MAX_PLY && self.killer_moves[ply].contains(&Some(mov))
+    }
+
+    fn history_score(&self, color: u8, mov: Move) -> i32 {
+        self.history[color as usize][mov.from() as usize][mov.to() as usize]
+    }
+
     fn update_killer(&mut self, mov: Move, ply: usize) {
         if ply < MAX_PLY {
             if Some(mov) != self.killer_moves[ply][0] {
@@ -98,32 +188,104 @@ impl MoveScorer {
     }
 }
 
+/// Most valuable victim, least valuable attacker; 0 for non-captures
+fn mvv_lva(board: &Board, mov: Move) -> i32 {
+    match (board.piece_at(mov.to()), board.piece_at(mov.from())) {
+        (Some((captured, _)), Some((attacker, _))) => 1 + (captured as i32) * 100 - (attacker as i32),
+        _ => 0,
+    }
+}
+
+/// Base late move reductions indexed by depth and move number:
+/// `0.75 + ln(depth) * ln(move) / 2.25`, rounded down
+fn lmr_table() -> Box<[[u8; 64]; 64]> {
+    let mut table = Box::new([[0u8; 64]; 64]);
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (index, reduction) in row.iter_mut().enumerate().skip(1) {
+            *reduction = (0.75 + (depth as f64).ln() * (index as f64).ln() / 2.25) as u8;
+        }
+    }
+    table
+}
+
 /// Chess engine searcher
 pub struct Searcher {
-    pub tt: TranspositionTable,
+    pub tt: Arc<TranspositionTable>,
     scorer: MoveScorer,
+    lmr: Box<[[u8; 64]; 64]>,
     stop_flag: Arc<AtomicBool>,
-    timer: Timer,
+    time: TimeManager,
     pub info: SearchInfo,
+    pub info_format: InfoFormat,
     ply: usize,
+    /// Number of search threads (Lazy SMP); 1 searches on the calling thread only
+    pub threads: usize,
+    /// 0 for the main thread, 1.. for helper threads
+    thread_id: usize,
+    /// Nodes searched, published by helper threads for the main thread to report
+    node_counter: Arc<AtomicU64>,
+    /// Node counters of the helpers during a multi-threaded search
+    helper_nodes: Vec<Arc<AtomicU64>>,
+    /// Helper searchers, kept between searches so their tables are built only once
+    helpers: Vec<Searcher>,
+    /// Stop flag shared by the helpers, raised when the main thread finishes
+    helper_stop: Arc<AtomicBool>,
+    /// Network to evaluate with instead of the classical evaluation
+    pub nnue: Option<Arc<Network>>,
+    /// Classical evaluation weights, taken from the active set when a search starts
+    params: Arc<EvalParams>,
 }
 
 impl Searcher {
     pub fn new(tt_size_mb: usize) -> Self {
+        Self::with_format(tt_size_mb, InfoFormat::Uci)
+    }
+
+    pub fn with_format(tt_size_mb: usize, info_format: InfoFormat) -> Self {
+        Self::with_shared(
+            Arc::new(TranspositionTable::new(tt_size_mb)),
+            Arc::new(AtomicBool::new(false)),
+            info_format,
+        )
+    }
+
+    /// Searcher using the given table and stop flag, which may be shared with other searchers
+    fn with_shared(tt: Arc<TranspositionTable>, stop_flag: Arc<AtomicBool>, info_format: InfoFormat) -> Self {
         Searcher {
-            tt: TranspositionTable::new(tt_size_mb),
+            tt,
             scorer: MoveScorer::new(),
-            stop_flag: Arc::new(AtomicBool::new(false)),
-            timer: Timer::new(),
+            lmr: lmr_table(),
+            stop_flag,
+            time: TimeManager::default(),
             info: SearchInfo {
                 nodes: 0,
                 depth: 0,
                 seldepth: 0,
                 score: 0,
+                bound: Bound::Exact,
                 pv: Vec::new(),
                 time_ms: 0,
             },
+            info_format,
             ply: 0,
+            threads: 1,
+            thread_id: 0,
+            node_counter: Arc::new(AtomicU64::new(0)),
+            helper_nodes: Vec::new(),
+            helpers: Vec::new(),
+            helper_stop: Arc::new(AtomicBool::new(false)),
+            nnue: None,
+            params: active_params(),
+        }
+    }
+
+    /// Resize the transposition table, discarding its contents
+    pub fn resize_tt(&mut self, size_mb: usize) {
+        // Helpers hold the table too; drop them so it can be resized in place
+        self.helpers.clear();
+        match Arc::get_mut(&mut self.tt) {
+            Some(tt) => tt.resize(size_mb),
+            None => self.tt = Arc::new(TranspositionTable::new(size_mb)),
         }
     }
 
@@ -135,10 +297,78 @@ impl Searcher {
         self.stop_flag.load(Ordering::Relaxed)
     }
 
+    /// Shared handle to the stop flag, so another thread can interrupt a running search
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_flag)
+    }
+
     /// Search with iterative deepening
-    pub fn search(&mut self, board: &mut Board, limits: SearchLimits) -> Move {
+    pub fn search(&mut self, board: &Board, limits: SearchLimits) -> Move {
         self.stop_flag.store(false, Ordering::Relaxed);
-        self.timer = Timer::new();
+        self.iterative_deepening(board, limits)
+    }
+
+    /// Iterative deepening without resetting the stop flag first, so a stop
+    /// request that arrives before a background search starts is still honoured.
+    /// The search runs on a copy of `board` with this searcher's network attached.
+    pub fn iterative_deepening(&mut self, board: &Board, limits: SearchLimits) -> Move {
+        self.tt.new_search();
+        let mut board = board.clone();
+        board.set_nnue(self.nnue.clone());
+        self.params = active_params();
+        if self.threads > 1 {
+            self.parallel_search(&mut board, limits)
+        } else {
+            self.deepen(&mut board, limits)
+        }
+    }
+
+    /// Lazy SMP: helper threads run their own iterative deepening on the same
+    /// position, sharing only the transposition table. Their results reach the
+    /// main thread through the table; the main thread alone applies the limits,
+    /// reports progress and picks the move, then stops the helpers.
+    fn parallel_search(&mut self, board: &mut Board, limits: SearchLimits) -> Move {
+        let helper_stop = Arc::clone(&self.helper_stop);
+        helper_stop.store(false, Ordering::Relaxed);
+        let mut helpers = std::mem::take(&mut self.helpers);
+        helpers.truncate(self.threads - 1);
+        for id in helpers.len() + 1..self.threads {
+            let mut helper = Searcher::with_shared(Arc::clone(&self.tt), Arc::clone(&helper_stop), InfoFormat::Silent);
+            helper.thread_id = id;
+            helpers.push(helper);
+        }
+        for helper in &mut helpers {
+            helper.params = Arc::clone(&self.params);
+            helper.node_counter.store(0, Ordering::Relaxed);
+        }
+        self.helper_nodes = helpers.iter().map(|h| Arc::clone(&h.node_counter)).collect();
+
+        let helper_limits = SearchLimits { depth: limits.depth, ..SearchLimits::default() };
+        let best_move = thread::scope(|scope| {
+            for helper in &mut helpers {
+                let mut board = board.clone();
+                scope.spawn(move || helper.deepen(&mut board, helper_limits));
+            }
+
+            let best_move = self.deepen(board, limits);
+            helper_stop.store(true, Ordering::Relaxed);
+            best_move
+        });
+
+        self.info.nodes += helpers.iter().map(|h| h.info.nodes).sum::<u64>();
+        self.helper_nodes.clear();
+        self.helpers = helpers;
+        best_move
+    }
+
+    /// Nodes searched so far by this thread and its helpers
+    fn total_nodes(&self) -> u64 {
+        self.info.nodes + self.helper_nodes.iter().map(|n| n.load(Ordering::Relaxed)).sum::<u64>()
+    }
+
+    /// Iterative deepening on this thread
+    fn deepen(&mut self, board: &mut Board, limits: SearchLimits) -> Move {
+        self.time = TimeManager::new(&limits, board.side);
         self.info.nodes = 0;
         self.scorer.clear();
         self.ply = 0;
@@ -146,39 +376,37 @@ impl Searcher {
         let max_depth = limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH);
         let mut best_move = Move::new(0, 0);
 
+        // Odd-numbered helpers start one ply deeper, so threads are spread over
+        // two depths instead of all searching the same tree
+        let first_depth = 1 + (self.thread_id % 2) as u8;
+
         // Iterative deepening
-        for depth in 1..=max_depth {
-            if self.is_stopped() {
+        for depth in first_depth..=max_depth {
+            if self.is_stopped() && depth > first_depth {
                 break;
             }
 
             self.info.depth = depth;
             self.info.seldepth = depth;
 
-            let score = self.alpha_beta(board, depth, 0, -MATE_SCORE, MATE_SCORE, true);
+            let score = self.aspiration_search(board, depth, &mut best_move);
 
-            if self.is_stopped() {
+            // Always keep the depth 1 result so there is a move to play
+            if self.is_stopped() && depth > first_depth {
                 break;
             }
 
-            self.info.score = score;
-            self.info.time_ms = self.timer.elapsed_ms();
-
-            // Extract PV from TT
-            self.info.pv = self.extract_pv(board, depth);
-            
+            self.update_info(board, score, Bound::Exact);
             if !self.info.pv.is_empty() {
                 best_move = self.info.pv[0];
             }
 
             // Print info
-            self.print_info();
+            self.print_info(board);
 
-            // Check time limit
-            if let Some(movetime) = limits.movetime {
-                if self.timer.elapsed_ms() >= movetime {
-                    break;
-                }
+            // Don't start an iteration that is unlikely to finish in time
+            if !self.time.can_start_iteration(self.total_nodes()) {
+                break;
             }
 
             // Check mate score
@@ -187,9 +415,70 @@ impl Searcher {
             }
         }
 
+        // Stopped before depth 1 completed: play any legal move
+        if best_move.from() == best_move.to() {
+            if let Some(&mov) = generate_legal_moves(board).first() {
+                best_move = mov;
+            }
+        }
+
         best_move
     }
 
+    /// Search the root with a narrow window around the previous iteration's score,
+    /// widening it on the failing side until the score falls inside. Fail-highs
+    /// update `best_move`, since the new move is already known to be better.
+    fn aspiration_search(&mut self, board: &mut Board, depth: u8, best_move: &mut Move) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH && mate_in(self.info.score).is_none() {
+            (self.info.score - delta, self.info.score + delta)
+        } else {
+            (-MATE_SCORE, MATE_SCORE)
+        };
+
+        loop {
+            let score = self.alpha_beta(board, depth, 0, alpha, beta, true);
+            if self.is_stopped() {
+                return score;
+            }
+
+            if score <= alpha && alpha > -MATE_SCORE {
+                alpha = (score - delta).max(-MATE_SCORE);
+                self.update_info(board, score, Bound::Upper);
+            } else if score >= beta && beta < MATE_SCORE {
+                beta = (score + delta).min(MATE_SCORE);
+                self.update_info(board, score, Bound::Lower);
+                if let Some(&mov) = self.info.pv.first() {
+                    *best_move = mov;
+                }
+            } else {
+                return score;
+            }
+
+            self.print_info(board);
+            delta *= 2;
+        }
+    }
+
+    /// Record an iteration's result and its principal variation from the TT
+    fn update_info(&mut self, board: &mut Board, score: i32, bound: Bound) {
+        self.info.score = score;
+        self.info.bound = bound;
+        self.info.time_ms = self.time.elapsed_ms();
+        self.info.pv = self.extract_pv(board, self.info.depth);
+    }
+
+    /// Stop the search once the time manager says so. Returns whether the search is stopped.
+    fn check_limits(&mut self) -> bool {
+        if self.thread_id > 0 {
+            self.node_counter.store(self.info.nodes, Ordering::Relaxed);
+        }
+        if !self.is_stopped() && self.time.must_stop(self.info.nodes, self.total_nodes()) {
+            self.stop();
+        }
+        self.is_stopped()
+    }
+
     /// Alpha-beta search with transposition table
     fn alpha_beta(
         &mut self,
@@ -200,8 +489,8 @@ impl Searcher {
         beta: i32,
         pv_node: bool,
     ) -> i32 {
-        if ply > 0 && (self.is_stopped() || ply >= MAX_PLY) {
-            return evaluate(board);
+        if ply > 0 && (self.check_limits() || ply >= MAX_PLY) {
+            return evaluate(board, &self.params);
         }
 
         // Update selective depth
@@ -209,8 +498,9 @@ impl Searcher {
             self.info.seldepth = ply as u8;
         }
 
-        // Check for draw by repetition or 50-move rule
-        if board.halfmove >= 100 {
+        // Check for draw by repetition or 50-move rule. A single repetition is
+        // enough inside the tree: if it was worth repeating once, it can be repeated again.
+        if ply > 0 && (board.halfmove >= 100 || board.is_repetition()) {
             return 0;
         }
 
@@ -220,10 +510,11 @@ impl Searcher {
 
         if let Some(entry) = tt_entry {
             if !pv_node && entry.depth >= depth {
+                let score = score_from_tt(entry.score, ply);
                 match entry.bound {
-                    Bound::Exact => return entry.score,
-                    Bound::Lower if entry.score >= beta => return entry.score,
-                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
                     _ => {}
                 }
             }
@@ -234,6 +525,34 @@ impl Searcher {
             return self.quiescence(board, ply, alpha, beta);
         }
 
+        let checked = in_check(board);
+
+        // Null-move pruning: let the opponent move twice in a row at reduced depth.
+        // If we still fail high, some real move will too. Not tried in check, at PV
+        // nodes, straight after another null move, or with only king and pawns
+        // left, where zugzwang makes passing better than any legal move.
+        if !pv_node
+            && !checked
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && !board.last_move_was_null()
+            && board.has_non_pawn_material(board.side)
+            && evaluate(board, &self.params) >= beta
+        {
+            let reduction = NULL_MOVE_REDUCTION + depth / 6;
+            board.make_null_move();
+            self.info.nodes += 1;
+            let score = -self.alpha_beta(board, depth.saturating_sub(1 + reduction), ply + 1, -beta, -beta + 1, false);
+            board.unmake_null_move();
+
+            if self.is_stopped() {
+                return 0;
+            }
+            if score >= beta {
+                // Mate scores found after passing are not proven, so don't return them
+                return if score >= MATE_SCORE - MAX_PLY as i32 { beta } else { score };
+            }
+        }
+
         // Generate moves
         let mut moves = Vec::with_capacity(64);
         generate_moves(board, &mut moves);
@@ -242,7 +561,7 @@ impl Searcher {
         let mut legal_moves = Vec::with_capacity(moves.len());
         for mov in moves {
             board.make_move(mov);
-            if !in_check(board) {
+            if !left_in_check(board) {
                 legal_moves.push(mov);
             }
             board.unmake_move();
@@ -250,7 +569,7 @@ impl Searcher {
 
         // Checkmate or stalemate
         if legal_moves.is_empty() {
-            return if in_check(board) {
+            return if checked {
                 -MATE_SCORE + ply as i32
             } else {
                 0
@@ -264,11 +583,39 @@ impl Searcher {
         let mut best_move = legal_moves[0];
         let mut bound = Bound::Upper;
 
-        for mov in legal_moves {
+        for (index, mov) in legal_moves.into_iter().enumerate() {
+            if self.check_limits() {
+                break;
+            }
+
+            let is_quiet = board.piece_at(mov.to()).is_none() && !mov.is_en_passant() && !mov.is_promotion();
+            let reduction = if is_quiet && !checked && depth >= LMR_MIN_DEPTH && index >= LMR_MIN_MOVES {
+                self.late_move_reduction(board, mov, depth, index, ply, pv_node)
+            } else {
+                0
+            };
+
             board.make_move(mov);
             self.info.nodes += 1;
 
-            let score = -self.alpha_beta(board, depth - 1, ply + 1, -beta, -alpha, pv_node && best_score == -MATE_SCORE);
+            let score = if index == 0 {
+                -self.alpha_beta(board, depth - 1, ply + 1, -beta, -alpha, pv_node)
+            } else {
+                // Checking moves are searched at full depth
+                let reduction = if reduction > 0 && in_check(board) { 0 } else { reduction };
+
+                // Principal variation search: prove the move is no better than alpha
+                // with a zero window, at reduced depth for late quiet moves, and
+                // re-search only when that fails
+                let mut score = -self.alpha_beta(board, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha, false);
+                if score > alpha && reduction > 0 {
+                    score = -self.alpha_beta(board, depth - 1, ply + 1, -alpha - 1, -alpha, false);
+                }
+                if score > alpha && score < beta {
+                    score = -self.alpha_beta(board, depth - 1, ply + 1, -beta, -alpha, pv_node);
+                }
+                score
+            };
 
             board.unmake_move();
 
@@ -293,17 +640,40 @@ impl Searcher {
             }
         }
 
-        // Store in transposition table
-        self.tt.store(board.hash, depth, best_score, Some(best_move), bound);
+        // Store in transposition table, unless the search was cut short
+        if !self.is_stopped() {
+            self.tt.store(board.hash, depth, score_to_tt(best_score, ply), Some(best_move), bound);
+        }
 
         best_score
     }
 
+    /// Depth reduction for a late quiet move: the logarithmic base amount, one
+    /// less at PV nodes, for killers and for moves with a good history, and never
+    /// so much that the move is searched below depth 1
+    fn late_move_reduction(&self, board: &Board, mov: Move, depth: u8, index: usize, ply: usize, pv_node: bool) -> u8 {
+        let mut reduction = self.lmr[(depth as usize).min(63)][index.min(63)] as i32;
+        if pv_node {
+            reduction -= 1;
+        }
+        if self.scorer.is_killer(mov, ply) {
+            reduction -= 1;
+        }
+        if self.scorer.history_score(board.side, mov) > LMR_HISTORY_THRESHOLD {
+            reduction -= 1;
+        }
+        reduction.clamp(0, depth as i32 - 2) as u8
+    }
+
     /// Quiescence search (only captures)
     fn quiescence(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.check_limits() {
+            return evaluate(board, &self.params);
+        }
         self.info.nodes += 1;
+        self.info.seldepth = self.info.seldepth.max(ply as u8);
 
-        let stand_pat = evaluate(board);
+        let stand_pat = evaluate(board, &self.params);
 
         if stand_pat >= beta {
             return beta;
@@ -317,20 +687,28 @@ impl Searcher {
         let mut moves = Vec::with_capacity(32);
         generate_captures(board, &mut moves);
 
-        // Filter to legal moves
+        // Filter to legal moves, skipping captures that lose material
         let mut legal_moves = Vec::with_capacity(moves.len());
         for mov in moves {
+            if loses_material(board, mov) {
+                continue;
+            }
             board.make_move(mov);
-            if !in_check(board) {
+            if !left_in_check(board) {
                 legal_moves.push(mov);
             }
             board.unmake_move();
         }
 
-        // Order moves by MVV-LVA
-        self.order_moves(board, &mut legal_moves, None, ply);
+        // Order moves by MVV-LVA. Losing captures are gone, so no exchange
+        // evaluation is needed to sort the rest.
+        legal_moves.sort_by_cached_key(|&mov| std::cmp::Reverse(mvv_lva(board, mov)));
 
         for mov in legal_moves {
+            if self.check_limits() {
+                break;
+            }
+
             board.make_move(mov);
 
             let score = -self.quiescence(board, ply + 1, -beta, -alpha);
@@ -372,6 +750,12 @@ impl Searcher {
         for _ in 0..max_depth {
             if let Some(entry) = self.tt.probe(board.hash) {
                 if let Some(mov) = entry.best_move {
+                    // Entries only store part of the key, so a colliding entry
+                    // can hold a move from another position
+                    if find_legal_move(board, mov) != Some(mov) {
+                        break;
+                    }
+
                     // Make the move
                     let original_side = board.side;
                     board.make_move(mov);
@@ -407,27 +791,149 @@ impl Searcher {
         pv
     }
 
-    /// Print search information
-    fn print_info(&self) {
-        let nps = if self.info.time_ms > 0 {
-            (self.info.nodes as u128 * 1000) / self.info.time_ms
+    /// Print search information in the configured protocol format. CECP has no
+    /// notation for bounds, so only exact scores are reported there.
+    fn print_info(&self, board: &mut Board) {
+        match self.info_format {
+            InfoFormat::Cecp if self.info.bound != Bound::Exact => {}
+            InfoFormat::Uci => self.print_uci_info(),
+            InfoFormat::Repl => self.print_repl_info(board),
+            InfoFormat::Cecp => self.print_cecp_info(),
+            InfoFormat::Silent => {}
+        }
+    }
+
+    fn nps(&self) -> u128 {
+        if self.info.time_ms > 0 {
+            (self.total_nodes() as u128 * 1000) / self.info.time_ms
         } else {
             0
-        };
+        }
+    }
+
+    /// UCI score, with `lowerbound`/`upperbound` after a failed aspiration search
+    fn score_string(&self) -> String {
+        let score = format_score(self.info.score);
+        match self.info.bound {
+            Bound::Exact => score,
+            Bound::Lower => score + " lowerbound",
+            Bound::Upper => score + " upperbound",
+        }
+    }
+
+    fn print_uci_info(&self) {
+        let nps = self.nps();
 
-        print!(
-            "info depth {} seldepth {} score cp {} nodes {} time {} nps {}",
-            self.info.depth, self.info.seldepth, self.info.score, 
-            self.info.nodes, self.info.time_ms, nps
+        let mut line = format!(
+            "info depth {} seldepth {} score {} nodes {} time {} nps {} hashfull {}",
+            self.info.depth, self.info.seldepth, self.score_string(),
+            self.total_nodes(), self.info.time_ms, nps, self.tt.hashfull()
         );
 
         if !self.info.pv.is_empty() {
-            print!(" pv");
+            line.push_str(" pv");
             for mov in &self.info.pv {
-                print!(" {}", mov.to_string());
+                line.push(' ');
+                line.push_str(&mov.to_string());
             }
         }
 
-        println!();
+        println!("{}", line);
+    }
+
+    fn print_repl_info(&self, board: &mut Board) {
+        let mut line = format!(
+            "info depth {} seldepth {} score {} nodes {} time {} nps {}",
+            self.info.depth, self.info.seldepth, self.score_string(),
+            self.total_nodes(), self.info.time_ms, self.nps()
+        );
+
+        if !self.info.pv.is_empty() {
+            line.push_str(" pv ");
+            line.push_str(&line_to_san(board, &self.info.pv).join(" "));
+        }
+
+        println!("{}", line);
+    }
+
+    /// CECP thinking output; time is in centiseconds and mates are reported as 100000 + moves
+    fn print_cecp_info(&self) {
+        let score = match mate_in(self.info.score) {
+            Some(moves) if moves > 0 => 100000 + moves,
+            Some(moves) => -100000 + moves,
+            None => self.info.score,
+        };
+
+        let mut line = format!(
+            "{} {} {} {}",
+            self.info.depth, score, self.info.time_ms / 10, self.total_nodes()
+        );
+
+        for mov in &self.info.pv {
+            line.push(' ');
+            line.push_str(&mov.to_string());
+        }
+
+        println!("{}", line);
+    }
+}
+
+/// Mate scores count plies from the root, but a position can be reached at any
+/// ply, so the table stores them counted from the position itself
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_SCORE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score < -(MATE_SCORE - MAX_PLY as i32) {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Convert a mate score from the table back to a distance from the root
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_SCORE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score < -(MATE_SCORE - MAX_PLY as i32) {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Number of moves to mate for mate scores (negative when being mated)
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() > MATE_SCORE - MAX_PLY as i32 {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        Some(if score > 0 { moves } else { -moves })
+    } else {
+        None
+    }
+}
+
+/// Format a score for UCI output ("cp 35" or "mate -3")
+pub fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_search() {
+        // Mate in one: Rd8#
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::with_format(1, InfoFormat::Silent);
+        searcher.threads = 3;
+
+        let limits = SearchLimits { depth: Some(5), ..SearchLimits::default() };
+        let best = searcher.search(&board, limits);
+        assert_eq!(best.to_string(), "d1d8");
+        assert_eq!(mate_in(searcher.info.score), Some(1));
+        assert_eq!(board.to_fen(), "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1");
     }
 }
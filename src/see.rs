@@ -0,0 +1,129 @@
+//! Static exchange evaluation: the material outcome of a sequence of captures on one square
+
+use crate::board::*;
+use crate::movegen::*;
+use crate::utils::*;
+
+/// Piece values used for exchanges. The king's is large enough that it is never
+/// worth capturing it into an attacked square.
+const SEE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000];
+
+/// Material gained by `mov` once both sides have made every capture on the target
+/// square that favours them, always recapturing with the least valuable piece.
+/// Sliders behind a capturing piece join the exchange as it leaves (x-rays).
+pub fn see(board: &Board, mov: Move) -> i32 {
+    let from = mov.from();
+    let to = mov.to();
+    let Some((mut attacker, mut side)) = board.piece_at(from) else {
+        return 0;
+    };
+
+    let mut occupied = board.all_occupancy() ^ bit_at(from);
+    let mut gain = [0i32; 32];
+
+    if mov.is_en_passant() {
+        let captured_sq = if side == WHITE { to - 8 } else { to + 8 };
+        occupied ^= bit_at(captured_sq);
+        gain[0] = SEE_VALUES[PAWN as usize];
+    } else if let Some((captured, _)) = board.piece_at(to) {
+        gain[0] = SEE_VALUES[captured as usize];
+    }
+    if mov.is_promotion() {
+        attacker = mov.promotion();
+        gain[0] += SEE_VALUES[attacker as usize] - SEE_VALUES[PAWN as usize];
+    }
+
+    let bishops = board.pieces[WHITE as usize][BISHOP as usize] | board.pieces[BLACK as usize][BISHOP as usize];
+    let rooks = board.pieces[WHITE as usize][ROOK as usize] | board.pieces[BLACK as usize][ROOK as usize];
+    let queens = board.pieces[WHITE as usize][QUEEN as usize] | board.pieces[BLACK as usize][QUEEN as usize];
+    let mut attackers = attackers_to(board, to, occupied) & occupied;
+    let mut depth = 0;
+
+    loop {
+        depth += 1;
+        side ^= 1;
+        // Score if the piece just moved to the target square is captured in turn
+        gain[depth] = SEE_VALUES[attacker as usize] - gain[depth - 1];
+        if depth == gain.len() - 1 {
+            break;
+        }
+
+        // Least valuable attacker of the side to recapture
+        let ours = attackers & board.occupancy[side as usize];
+        let Some(piece) = (PAWN..=KING).find(|&p| board.pieces[side as usize][p as usize] & ours != 0) else {
+            break;
+        };
+        let bb = board.pieces[side as usize][piece as usize] & ours;
+        occupied ^= bb & bb.wrapping_neg();
+
+        // Reveal sliders that were behind the piece that just captured
+        if matches!(piece, PAWN | BISHOP | QUEEN) {
+            attackers |= bishop_attacks(to, occupied) & (bishops | queens);
+        }
+        if matches!(piece, ROOK | QUEEN) {
+            attackers |= rook_attacks(to, occupied) & (rooks | queens);
+        }
+        attackers &= occupied;
+        attacker = piece;
+    }
+
+    // The last entry is speculative: nobody was left to make that capture
+    while depth > 1 {
+        depth -= 1;
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+    }
+    gain[0]
+}
+
+/// Value of a piece for exchange purposes
+#[inline]
+pub fn see_value(piece: u8) -> i32 {
+    SEE_VALUES[piece as usize]
+}
+
+/// Whether `mov` loses material in the exchange on its target square. Capturing
+/// a piece worth at least as much as the capturer never does, so the full exchange
+/// is only worked out for the rest.
+pub fn loses_material(board: &Board, mov: Move) -> bool {
+    if let (Some((captured, _)), Some((attacker, _))) = (board.piece_at(mov.to()), board.piece_at(mov.from())) {
+        if SEE_VALUES[captured as usize] >= SEE_VALUES[attacker as usize] {
+            return false;
+        }
+    }
+    see(board, mov) < 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see_of(fen: &str, mov: &str) -> i32 {
+        let mut board = Board::from_fen(fen).unwrap();
+        let mov = find_legal_move(&mut board, Move::from_string(mov).unwrap()).unwrap();
+        see(&board, mov)
+    }
+
+    #[test]
+    fn test_see() {
+        // Undefended pawn
+        assert_eq!(see_of("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+        // Knight takes a pawn and is lost to the knight behind it
+        assert_eq!(see_of("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"), -220);
+        // Queen takes a pawn defended by a pawn
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "e1e5"), -800);
+        // Pawn takes a defended knight
+        assert_eq!(see_of("4k3/8/5p2/4n3/3P4/8/8/4K3 w - - 0 1", "d4e5"), 220);
+        // Quiet move to an undefended square
+        assert_eq!(see_of("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a5"), 0);
+    }
+
+    #[test]
+    fn test_see_xray() {
+        // The rook behind on e1 recaptures after Rxe5 Rxe5
+        assert_eq!(see_of("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), 100);
+        // Without the second rook the capture loses the exchange
+        assert_eq!(see_of("4r1k1/8/8/4p3/8/8/4R3/6K1 w - - 0 1", "e2e5"), -400);
+        // Queen behind a bishop on the diagonal
+        assert_eq!(see_of("6k1/8/5p2/4n3/8/2B5/1Q6/6K1 w - - 0 1", "c3e5"), 90);
+    }
+}
@@ -0,0 +1,157 @@
+//! Time management: soft and hard per-move time limits and node budgets
+
+use crate::board::WHITE;
+use crate::search::SearchLimits;
+use crate::utils::Timer;
+
+/// Time kept back for communication lag, in milliseconds
+const MOVE_OVERHEAD_MS: u128 = 50;
+/// Moves assumed to remain when the time control doesn't say
+const DEFAULT_MOVES_TO_GO: u128 = 30;
+/// Nodes searched between clock checks (a power of two)
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Tracks the clock and node count of one search against its limits
+pub struct TimeManager {
+    timer: Timer,
+    /// Don't start another iteration after this many milliseconds
+    soft_limit: Option<u128>,
+    /// Abort the search mid-iteration after this many milliseconds
+    hard_limit: Option<u128>,
+    node_limit: Option<u64>,
+}
+
+impl TimeManager {
+    /// Start the clock for a search by `side` under `limits`. A fixed movetime
+    /// takes precedence over the game clock.
+    pub fn new(limits: &SearchLimits, side: u8) -> Self {
+        let (time_left, increment) = if side == WHITE {
+            (limits.wtime, limits.winc)
+        } else {
+            (limits.btime, limits.binc)
+        };
+
+        let (soft_limit, hard_limit) = match (limits.movetime, time_left) {
+            (Some(movetime), _) => (Some(movetime), Some(movetime)),
+            (None, Some(time_left)) => {
+                let (soft, hard) = allocate_time(time_left, increment, limits.movestogo);
+                (Some(soft), Some(hard))
+            }
+            (None, None) => (None, None),
+        };
+
+        TimeManager {
+            timer: Timer::new(),
+            soft_limit,
+            hard_limit,
+            node_limit: limits.nodes,
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> u128 {
+        self.timer.elapsed_ms()
+    }
+
+    #[cfg(test)]
+    pub fn soft_limit(&self) -> Option<u128> {
+        self.soft_limit
+    }
+
+    #[cfg(test)]
+    pub fn hard_limit(&self) -> Option<u128> {
+        self.hard_limit
+    }
+
+    /// Whether there is time for another iteration of iterative deepening
+    pub fn can_start_iteration(&self, nodes: u64) -> bool {
+        self.soft_limit.is_none_or(|soft| self.elapsed_ms() < soft)
+            && self.node_limit.is_none_or(|limit| nodes < limit)
+    }
+
+    /// Whether the search must stop now. The node budget is checked exactly
+    /// against `total_nodes`, summed over all search threads; the clock only
+    /// every `CHECK_INTERVAL` of this thread's `nodes`, as reading it is comparatively slow.
+    pub fn must_stop(&self, nodes: u64, total_nodes: u64) -> bool {
+        if self.node_limit.is_some_and(|limit| total_nodes >= limit) {
+            return true;
+        }
+        nodes & (CHECK_INTERVAL - 1) == 0 && self.hard_limit.is_some_and(|hard| self.elapsed_ms() >= hard)
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self::new(&SearchLimits::default(), WHITE)
+    }
+}
+
+/// Split the remaining clock time into soft and hard limits for one move. The
+/// soft limit is an even share of the time left plus most of the increment; the
+/// hard limit allows up to four times that for an unfinished iteration. Neither
+/// is ever more than 40% of the clock, after a margin for communication lag.
+pub fn allocate_time(time_left: u128, increment: u128, movestogo: Option<u128>) -> (u128, u128) {
+    let usable = time_left.saturating_sub(MOVE_OVERHEAD_MS).max(1);
+    let moves = movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, DEFAULT_MOVES_TO_GO);
+
+    let cap = (usable * 2 / 5).max(1);
+
+    let soft = (time_left / moves + increment * 3 / 4).clamp(1, cap);
+    let hard = (soft * 4).min(cap);
+    (soft, hard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, BLACK};
+    use crate::search::{InfoFormat, Searcher};
+
+    #[test]
+    fn test_allocate_time() {
+        // One minute, no increment: 1/30 of the clock, up to four times that
+        assert_eq!(allocate_time(60_000, 0, None), (2000, 8000));
+        // Increment adds to the budget
+        assert_eq!(allocate_time(60_000, 1000, None), (2750, 11_000));
+        // Last move before the time control: as much as the cap allows
+        assert_eq!(allocate_time(10_000, 0, Some(1)), (3980, 3980));
+        // A large increment doesn't lift either limit past 40% of the clock
+        assert_eq!(allocate_time(1000, 2000, None), (380, 380));
+        assert_eq!(allocate_time(30, 0, None), (1, 1));
+    }
+
+    #[test]
+    fn test_limits_per_side() {
+        let limits = SearchLimits {
+            wtime: Some(60_000),
+            btime: Some(3000),
+            ..SearchLimits::default()
+        };
+        assert_eq!(TimeManager::new(&limits, WHITE).soft_limit(), Some(2000));
+        assert_eq!(TimeManager::new(&limits, BLACK).soft_limit(), Some(100));
+
+        let limits = SearchLimits { movetime: Some(500), ..limits };
+        assert_eq!(TimeManager::new(&limits, BLACK).hard_limit(), Some(500));
+
+        assert_eq!(TimeManager::default().hard_limit(), None);
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let board = Board::starting_position();
+        let mut searcher = Searcher::with_format(1, InfoFormat::Silent);
+        let limits = SearchLimits {
+            nodes: Some(5000),
+            ..SearchLimits::default()
+        };
+
+        let best = searcher.search(&board, limits);
+        assert!(searcher.info.nodes <= 5000, "searched {} nodes", searcher.info.nodes);
+        assert_ne!(best.from(), best.to());
+
+        // The budget counts the nodes of all threads, not just the calling one
+        let time = TimeManager::new(&limits, WHITE);
+        assert!(!time.must_stop(3000, 4999));
+        assert!(time.must_stop(3000, 5000));
+        assert!(!time.can_start_iteration(5000));
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::board::*;
 use crate::movegen::*;
+use crate::san::move_to_san;
 use crate::utils::*;
 
 pub struct TipsEngine;
@@ -222,10 +223,10 @@ impl TipsEngine {
         if !best_moves.is_empty() {
             best_moves.sort_by(|a, b| b.1.cmp(&a.1));
             let hint_move = best_moves[0].0;
-            format!("💭 HINT: Consider {} (captures center, or gives check)", hint_move.to_string())
+            format!("💭 HINT: Consider {} (captures center, or gives check)", move_to_san(board, hint_move))
         } else {
             let random_move = legal_moves[board.fullmove as usize % legal_moves.len()];
-            format!("💭 HINT: {} is a decent move", random_move.to_string())
+            format!("💭 HINT: {} is a decent move", move_to_san(board, random_move))
         }
     }
 }
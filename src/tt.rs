@@ -1,6 +1,7 @@
 //! Transposition table for caching search results.
 
 use crate::utils::Move;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 
 /// Entry bound types
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -13,95 +14,188 @@ pub enum Bound {
 /// Transposition table entry
 #[derive(Copy, Clone)]
 pub struct TTEntry {
-    pub hash: u64,
     pub depth: u8,
     pub score: i32,
     pub best_move: Option<Move>,
     pub bound: Bound,
 }
 
-impl TTEntry {
-    pub const fn empty() -> Self {
-        TTEntry {
-            hash: 0,
-            depth: 0,
-            score: 0,
-            best_move: None,
-            bound: Bound::Exact,
-        }
+/// Entries per bucket; eight 64-bit entries fill one cache line
+const BUCKET_SIZE: usize = 8;
+/// Generations are stored in 6 bits and wrap around
+const AGE_MASK: u8 = 63;
+/// Entries sampled for the hashfull estimate
+const HASHFULL_SAMPLE: usize = 1000;
+
+/// A cache line of entries. Each entry is packed into a single 64-bit word,
+/// so threads can read and write it without locks and never see a torn entry:
+/// move in bits 0-15, score 16-31, depth 32-39, bound 40-41, age 42-47 and
+/// the low 16 bits of the position key in 48-63. Zero means empty.
+#[repr(align(64))]
+#[derive(Default)]
+struct Bucket {
+    entries: [AtomicU64; BUCKET_SIZE],
+}
+
+fn pack(key: u16, depth: u8, score: i32, best_move: Option<Move>, bound: Bound, age: u8) -> u64 {
+    let mov = best_move.map_or(0, |m| m.0) as u64;
+    let bound = match bound {
+        Bound::Exact => 0u64,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    mov | ((score as i16 as u16 as u64) << 16)
+        | ((depth as u64) << 32)
+        | (bound << 40)
+        | (((age & AGE_MASK) as u64) << 42)
+        | ((key as u64) << 48)
+}
+
+fn unpack(data: u64) -> TTEntry {
+    TTEntry {
+        depth: (data >> 32) as u8,
+        score: (data >> 16) as u16 as i16 as i32,
+        best_move: Some(Move(data as u16)).filter(|&m| m != Move::NULL),
+        bound: match (data >> 40) & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        },
     }
 }
 
-/// Transposition table
+#[inline]
+const fn key_of(data: u64) -> u16 {
+    (data >> 48) as u16
+}
+
+#[inline]
+const fn depth_of(data: u64) -> u8 {
+    (data >> 32) as u8
+}
+
+#[inline]
+const fn age_of(data: u64) -> u8 {
+    ((data >> 42) as u8) & AGE_MASK
+}
+
+/// Transposition table, shared between search threads
 pub struct TranspositionTable {
-    table: Vec<TTEntry>,
-    size: usize,
+    buckets: Vec<Bucket>,
+    /// Current search generation, used to prefer replacing entries from old searches
+    age: AtomicU8,
 }
 
 impl TranspositionTable {
     /// Create a new transposition table with given size in MB
     pub fn new(size_mb: usize) -> Self {
-        let entry_size = std::mem::size_of::<TTEntry>();
-        let num_entries = (size_mb * 1024 * 1024) / entry_size;
-        // Round to power of 2 for efficient modulo
-        let size = num_entries.next_power_of_two();
-        
+        let count = (size_mb * 1024 * 1024 / std::mem::size_of::<Bucket>()).max(1);
         TranspositionTable {
-            table: vec![TTEntry::empty(); size],
-            size,
+            buckets: (0..count).map(|_| Bucket::default()).collect(),
+            age: AtomicU8::new(0),
         }
     }
 
-    /// Get index for a hash
+    /// Reallocate the table with a new size in MB, discarding its contents
+    pub fn resize(&mut self, size_mb: usize) {
+        // Free the old table first so both are never allocated at once
+        self.buckets = Vec::new();
+        *self = Self::new(size_mb);
+    }
+
+    /// Bucket for a hash. Multiplying by the bucket count and keeping the high
+    /// bits maps the hash onto any table size, not only powers of two.
     #[inline]
-    fn index(&self, hash: u64) -> usize {
-        (hash as usize) & (self.size - 1)
+    fn bucket(&self, hash: u64) -> &Bucket {
+        let index = ((hash as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[index]
+    }
+
+    /// Start a new search generation. Entries from earlier searches stay usable
+    /// but are replaced first.
+    pub fn new_search(&self) {
+        self.age.store(self.age.load(Ordering::Relaxed).wrapping_add(1) & AGE_MASK, Ordering::Relaxed);
     }
 
     /// Probe the transposition table
     #[inline]
-    pub fn probe(&self, hash: u64) -> Option<&TTEntry> {
-        let entry = &self.table[self.index(hash)];
-        if entry.hash == hash {
-            Some(entry)
-        } else {
-            None
-        }
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        let key = hash as u16;
+        self.bucket(hash)
+            .entries
+            .iter()
+            .map(|entry| entry.load(Ordering::Relaxed))
+            .find(|&data| data != 0 && key_of(data) == key)
+            .map(unpack)
     }
 
-    /// Store an entry in the transposition table
+    /// Store an entry in the transposition table. An existing entry for the
+    /// position is overwritten unless it is from this search and much deeper;
+    /// otherwise the entry that is shallowest, once older generations are
+    /// discounted, is replaced.
     #[inline]
-    pub fn store(&mut self, hash: u64, depth: u8, score: i32, best_move: Option<Move>, bound: Bound) {
-        let idx = self.index(hash);
-        let entry = &mut self.table[idx];
-        
-        // Always replace scheme (can be improved with depth-preferred replacement)
-        if entry.hash != hash || depth >= entry.depth {
-            *entry = TTEntry {
-                hash,
-                depth,
-                score,
-                best_move,
-                bound,
-            };
+    pub fn store(&self, hash: u64, depth: u8, score: i32, best_move: Option<Move>, bound: Bound) {
+        let key = hash as u16;
+        let age = self.age.load(Ordering::Relaxed);
+        let entries = &self.bucket(hash).entries;
+
+        let mut victim = 0;
+        let mut victim_value = i32::MAX;
+        for (i, entry) in entries.iter().enumerate() {
+            let data = entry.load(Ordering::Relaxed);
+            if data == 0 {
+                if victim_value > i32::MIN {
+                    victim = i;
+                    victim_value = i32::MIN;
+                }
+                continue;
+            }
+
+            if key_of(data) == key {
+                if bound != Bound::Exact && age_of(data) == age && depth + 2 < depth_of(data) {
+                    return;
+                }
+                // Keep the old move if this search didn't produce one
+                let best_move = best_move.or(unpack(data).best_move);
+                entry.store(pack(key, depth, score, best_move, bound, age), Ordering::Relaxed);
+                return;
+            }
+
+            let relative_age = (age.wrapping_sub(age_of(data)) & AGE_MASK) as i32;
+            let value = depth_of(data) as i32 - 8 * relative_age;
+            if value < victim_value {
+                victim = i;
+                victim_value = value;
+            }
         }
+
+        entries[victim].store(pack(key, depth, score, best_move, bound, age), Ordering::Relaxed);
     }
 
     /// Clear the transposition table
-    pub fn clear(&mut self) {
-        for entry in &mut self.table {
-            *entry = TTEntry::empty();
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            for entry in &bucket.entries {
+                entry.store(0, Ordering::Relaxed);
+            }
         }
+        self.age.store(0, Ordering::Relaxed);
     }
 
-    /// Get the number of used entries (for statistics)
-    pub fn used_entries(&self) -> usize {
-        self.table.iter().filter(|e| e.hash != 0).count()
-    }
-
-    /// Get fill percentage
-    pub fn fill_percentage(&self) -> f64 {
-        (self.used_entries() as f64 / self.size as f64) * 100.0
+    /// Permille of the table holding entries from the current search, estimated
+    /// from the first buckets (for UCI `hashfull`)
+    pub fn hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sampled = HASHFULL_SAMPLE.min(self.buckets.len() * BUCKET_SIZE);
+        let used = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.entries.iter())
+            .take(sampled)
+            .map(|entry| entry.load(Ordering::Relaxed))
+            .filter(|&data| data != 0 && age_of(data) == age)
+            .count();
+        used * 1000 / sampled
     }
 }
 
@@ -110,3 +204,85 @@ impl Default for TranspositionTable {
         Self::new(64) // 64 MB default
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let mov = Move::new(12, 28);
+        tt.store(0x1234_5678_9abc_def0, 7, -29_990, Some(mov), Bound::Lower);
+
+        let entry = tt.probe(0x1234_5678_9abc_def0).unwrap();
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.score, -29_990);
+        assert_eq!(entry.best_move, Some(mov));
+        assert!(entry.bound == Bound::Lower);
+        assert!(tt.probe(0x1234_5678_9abc_def1).is_none());
+
+        // A shallower result without a move keeps the stored move
+        tt.store(0x1234_5678_9abc_def0, 6, 15, None, Bound::Exact);
+        let entry = tt.probe(0x1234_5678_9abc_def0).unwrap();
+        assert_eq!((entry.depth, entry.score, entry.best_move), (6, 15, Some(mov)));
+
+        tt.clear();
+        assert!(tt.probe(0x1234_5678_9abc_def0).is_none());
+    }
+
+    #[test]
+    fn test_replacement_and_hashfull() {
+        let mut tt = TranspositionTable::new(1);
+        // Keys that differ only in their low bits land in the same bucket
+        let hash = |i: u64| 0xabcd_0000_0000_0000 | i;
+
+        for i in 0..BUCKET_SIZE as u64 {
+            tt.store(hash(i), 10 + i as u8, 0, None, Bound::Exact);
+        }
+        // A full bucket loses its shallowest entry
+        tt.store(hash(100), 1, 0, None, Bound::Exact);
+        assert!(tt.probe(hash(0)).is_none());
+        assert!(tt.probe(hash(100)).is_some());
+
+        // In the next search, entries from the last one count as 8 plies shallower
+        tt.new_search();
+        tt.store(hash(101), 5, 0, None, Bound::Exact);
+        tt.store(hash(102), 5, 0, None, Bound::Exact);
+        assert!(tt.probe(hash(100)).is_none());
+        assert!(tt.probe(hash(1)).is_none());
+        assert!(tt.probe(hash(101)).is_some());
+        assert!(tt.probe(hash(102)).is_some());
+        assert!(tt.probe(hash(2)).is_some());
+
+        tt.resize(2);
+        assert_eq!(tt.buckets.len(), 2 * 1024 * 1024 / 64);
+        assert!(tt.probe(hash(101)).is_none());
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.buckets.len(), 1 << 14);
+        // The top 14 bits of a key pick its bucket
+        let hash = |bucket: u64, i: u64| (bucket << 50) | i;
+
+        // Fill the first 25 of the 125 sampled buckets: 200 of 1000 entries
+        for bucket in 0..25 {
+            for i in 0..BUCKET_SIZE as u64 {
+                tt.store(hash(bucket, i), 1, 0, None, Bound::Exact);
+            }
+        }
+        // Entries outside the sample aren't counted
+        tt.store(hash(1000, 0), 1, 0, None, Bound::Exact);
+        assert_eq!(tt.hashfull(), 200);
+
+        // Only entries from the current search count
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+        for bucket in 0..5 {
+            tt.store(hash(bucket, 100), 1, 0, None, Bound::Exact);
+        }
+        assert_eq!(tt.hashfull(), 5);
+    }
+}
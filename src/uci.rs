@@ -0,0 +1,236 @@
+//! UCI (Universal Chess Interface) protocol front-end for GUIs and match runners
+
+use crate::board::*;
+use crate::movegen::*;
+use crate::nnue::Network;
+use crate::params::{set_active_params, EvalParams};
+use crate::search::*;
+use crate::utils::*;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const ENGINE_NAME: &str = "Chess Engine 2.0";
+const ENGINE_AUTHOR: &str = "Chess Engine authors";
+
+const DEFAULT_HASH_MB: usize = 64;
+const MAX_HASH_MB: usize = 4096;
+const MAX_THREADS: usize = 256;
+
+/// UCI engine state: the current position plus a searcher that runs on a background thread
+pub struct UciEngine {
+    board: Board,
+    searcher: Arc<Mutex<Searcher>>,
+    stop_flag: Arc<AtomicBool>,
+    search_thread: Option<JoinHandle<()>>,
+    /// Network loaded with `EvalFile`, used for searches while `UseNNUE` is on
+    network: Option<Arc<Network>>,
+    use_nnue: bool,
+}
+
+impl UciEngine {
+    pub fn new() -> Self {
+        let searcher = Searcher::new(DEFAULT_HASH_MB);
+        let stop_flag = searcher.stop_handle();
+        UciEngine {
+            board: Board::starting_position(),
+            searcher: Arc::new(Mutex::new(searcher)),
+            stop_flag,
+            search_thread: None,
+            network: None,
+            use_nnue: false,
+        }
+    }
+
+    /// Read commands from stdin until `quit` or end of input
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break,
+            };
+            if !self.handle_command(&line) {
+                break;
+            }
+        }
+        self.stop_search();
+    }
+
+    /// Handle a single command line. Returns false when the engine should exit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.is_empty() {
+            return true;
+        }
+
+        match parts[0] {
+            "uci" => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH_MB, MAX_HASH_MB);
+                println!("option name Clear Hash type button");
+                println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                println!("option name EvalFile type string default <empty>");
+                println!("option name UseNNUE type check default false");
+                println!("option name EvalParams type string default <empty>");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.wait_for_search();
+                self.board = Board::starting_position();
+                self.searcher.lock().unwrap().tt.clear();
+            }
+            "position" => {
+                self.wait_for_search();
+                if let Err(e) = self.set_position(&parts[1..]) {
+                    println!("info string {}", e);
+                }
+            }
+            "go" => {
+                self.wait_for_search();
+                self.start_search(&parts[1..]);
+            }
+            "stop" => self.stop_search(),
+            "setoption" => {
+                self.wait_for_search();
+                self.set_option(&parts[1..]);
+            }
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            "d" => println!("{}", self.board.to_fen()),
+            // "debug" and "register" need no action; anything else is ignored per the protocol
+            _ => {}
+        }
+
+        true
+    }
+
+    /// Handle `position startpos|fen <fen> [moves ...]`
+    fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_idx = args.iter().position(|&a| a == "moves").unwrap_or(args.len());
+
+        let mut board = match args.first() {
+            Some(&"startpos") => Board::starting_position(),
+            Some(&"fen") => Board::from_fen(&args[1..moves_idx].join(" "))?,
+            _ => return Err("Usage: position startpos|fen <fen> [moves ...]".to_string()),
+        };
+
+        for move_str in args.iter().skip(moves_idx + 1) {
+            let mov = Move::from_string(move_str)
+                .and_then(|m| find_legal_move(&mut board, m))
+                .ok_or_else(|| format!("Illegal move in position command: {}", move_str))?;
+            board.make_move(mov);
+        }
+
+        self.board = board;
+        Ok(())
+    }
+
+    /// Handle `go [depth N] [movetime MS] [nodes N] [wtime MS btime MS winc MS binc MS movestogo N] [infinite]`
+    fn start_search(&mut self, args: &[&str]) {
+        let limits = SearchLimits::parse(args);
+        let infinite = args.contains(&"infinite");
+
+        self.stop_flag.store(false, Ordering::Relaxed);
+
+        let searcher = Arc::clone(&self.searcher);
+        let stop_flag = Arc::clone(&self.stop_flag);
+        let mut board = self.board.clone();
+
+        self.search_thread = Some(thread::spawn(move || {
+            let best_move = searcher.lock().unwrap().iterative_deepening(&board, limits);
+
+            // In infinite mode the GUI expects bestmove only after it sends stop
+            while infinite && !stop_flag.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            if generate_legal_moves(&mut board).is_empty() {
+                println!("bestmove 0000");
+            } else {
+                println!("bestmove {}", best_move.to_string());
+            }
+        }));
+    }
+
+    /// Handle `setoption name <id> [value <x>]`
+    fn set_option(&mut self, args: &[&str]) {
+        let value_idx = args.iter().position(|&a| a == "value").unwrap_or(args.len());
+        let name = args.get(1..value_idx).map(|n| n.join(" ")).unwrap_or_default();
+        let value = args.get(value_idx + 1..).map(|v| v.join(" ")).unwrap_or_default();
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => {
+                    self.searcher.lock().unwrap().resize_tt(mb);
+                }
+                _ => println!("info string Invalid Hash value: {}", value),
+            },
+            "clear hash" => self.searcher.lock().unwrap().tt.clear(),
+            "threads" => match value.parse::<usize>() {
+                Ok(n) if (1..=MAX_THREADS).contains(&n) => self.searcher.lock().unwrap().threads = n,
+                _ => println!("info string Invalid Threads value: {}", value),
+            },
+            "evalfile" => match Network::load(&value) {
+                Ok(net) => {
+                    println!("info string Loaded network {} ({} hidden neurons)", value, net.hidden_size());
+                    self.network = Some(Arc::new(net));
+                    self.update_evaluator();
+                }
+                Err(e) => println!("info string {}", e),
+            },
+            "evalparams" => {
+                if value.is_empty() || value == "<empty>" {
+                    set_active_params(None);
+                } else {
+                    match EvalParams::load(&value) {
+                        Ok(params) => {
+                            set_active_params(Some(params));
+                            println!("info string Loaded evaluation weights from {}", value);
+                        }
+                        Err(e) => println!("info string {}", e),
+                    }
+                }
+            }
+            "usennue" => {
+                self.use_nnue = value == "true";
+                if self.use_nnue && self.network.is_none() {
+                    println!("info string No network loaded; set EvalFile first");
+                }
+                self.update_evaluator();
+            }
+            _ => println!("info string Unknown option: {}", name),
+        }
+    }
+
+    /// Search with the loaded network if NNUE is enabled, else the classical evaluation
+    fn update_evaluator(&mut self) {
+        let network = self.network.clone().filter(|_| self.use_nnue);
+        self.searcher.lock().unwrap().nnue = network;
+    }
+
+    /// Interrupt the running search (if any) and wait for its bestmove
+    fn stop_search(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    /// Wait for a running search to finish on its own
+    fn wait_for_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -48,6 +48,25 @@ impl UI {
         println!("└─────────────────────────────────────────────────┘\n");
     }
 
+    /// Most recent rating changes, newest first
+    pub fn print_rating_history(user: &User, count: usize) {
+        if user.rating_history.is_empty() {
+            return;
+        }
+
+        println!(" Recent rated games:");
+        for change in user.rating_history.iter().rev().take(count) {
+            let result = match change.score {
+                s if s > 0.75 => "won",
+                s if s > 0.25 => "drew",
+                _ => "lost",
+            };
+            println!("   {} vs {} ({}): {} -> {} ({:+})", result, change.opponent, change.opponent_rating,
+                     change.before, change.after, change.after as i64 - change.before as i64);
+        }
+        println!();
+    }
+
     pub fn display_board_fancy(board: &Board, show_coordinates: bool, highlight_last_move: Option<Move>) {
         println!("\n    ╔═══╤═══╤═══╤═══╤═══╤═══╤═══╤═══╗");
 
@@ -131,16 +150,24 @@ impl UI {
         println!("\n╔════════════════════ COMMANDS ══════════════════════╗");
         println!("║ new         - Start a new game                     ║");
         println!("║ show/d      - Display the board                    ║");
-        println!("║ <move>      - Make a move (e.g., e2e4, e7e8q)      ║");
+        println!("║ <move>      - Make a move (e.g., Nf3, exd5, e2e4)  ║");
         println!("║ undo/u      - Undo last move                       ║");
+        println!("║ claim       - Claim a draw (repetition, 50 moves)  ║");
         println!("║ hint        - Get a move suggestion                ║");
         println!("║ tip         - Get a chess tip                      ║");
         println!("║ save        - Save current game                    ║");
         println!("║ load        - Load a saved game                    ║");
+        println!("║ export F    - Export the game to PGN file F        ║");
+        println!("║ import F [N]- Import game N from PGN file F        ║");
         println!("║ stats       - Show your statistics                 ║");
+        println!("║ play W|B [L]- Play the computer (easy/medium/hard) ║");
+        println!("║ versus USER - Rated game against another user      ║");
         println!("║ go depth N  - Computer search to depth N           ║");
         println!("║ perft N     - Run perft test                       ║");
         println!("║ eval        - Show position evaluation             ║");
+        println!("║ eval trace  - Evaluation term by term (add json)   ║");
+        println!("║ nnue F|off  - Evaluate with NNUE file F, or not    ║");
+        println!("║ params ...  - Load/save/reset evaluation weights   ║");
         println!("║ logout      - Logout and switch user               ║");
         println!("║ help/h      - Show this menu                       ║");
         println!("║ quit/q      - Exit                                 ║");
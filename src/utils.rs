@@ -11,6 +11,9 @@ use std::time::Instant;
 pub struct Move(pub u16);
 
 impl Move {
+    /// Placeholder for "no move", also used for null moves in the board history
+    pub const NULL: Move = Move(0);
+
     #[inline]
     pub const fn new(from: u8, to: u8) -> Self {
         Move(((to as u16) << 6) | (from as u16))
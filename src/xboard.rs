@@ -0,0 +1,343 @@
+//! XBoard/CECP (Chess Engine Communication Protocol) front-end
+
+use crate::board::*;
+use crate::gamestatus::*;
+use crate::movegen::*;
+use crate::search::*;
+use crate::utils::*;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+const ENGINE_NAME: &str = "Chess Engine 2.0";
+const DEFAULT_HASH_MB: usize = 64;
+/// Time control assumed until the GUI sends `level`, `st` or `time`: 40 moves in 5 minutes
+const DEFAULT_MOVES_PER_SESSION: u16 = 40;
+const DEFAULT_BASE_MS: u128 = 5 * 60 * 1000;
+
+/// CECP engine state. The board is shared with the search thread, which plays
+/// its move on it as soon as the search finishes.
+pub struct XboardEngine {
+    board: Arc<Mutex<Board>>,
+    searcher: Arc<Mutex<Searcher>>,
+    stop_flag: Arc<AtomicBool>,
+    /// Set when a running search must not play its move (e.g. after `force` or `new`)
+    discard_move: Arc<AtomicBool>,
+    search_thread: Option<JoinHandle<()>>,
+    /// Side the engine plays, or None in force mode
+    engine_side: Option<u8>,
+    post: bool,
+    /// Depth limit from `sd`
+    depth_limit: Option<u8>,
+    /// Fixed time per move from `st`, in milliseconds
+    time_per_move: Option<u128>,
+    /// Moves per time control from `level` (0 = whole game)
+    moves_per_session: u16,
+    /// Increment from `level`, in milliseconds
+    increment_ms: u128,
+    /// Engine clock from `time`, in milliseconds
+    time_left_ms: Option<u128>,
+}
+
+impl XboardEngine {
+    pub fn new() -> Self {
+        let searcher = Searcher::new(DEFAULT_HASH_MB);
+        let stop_flag = searcher.stop_handle();
+        XboardEngine {
+            board: Arc::new(Mutex::new(Board::starting_position())),
+            searcher: Arc::new(Mutex::new(searcher)),
+            stop_flag,
+            discard_move: Arc::new(AtomicBool::new(false)),
+            search_thread: None,
+            engine_side: Some(BLACK),
+            post: false,
+            depth_limit: None,
+            time_per_move: None,
+            moves_per_session: DEFAULT_MOVES_PER_SESSION,
+            increment_ms: 0,
+            time_left_ms: Some(DEFAULT_BASE_MS),
+        }
+    }
+
+    /// Read commands from stdin until `quit` or end of input
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break,
+            };
+            if !self.handle_command(&line) {
+                break;
+            }
+        }
+        self.abort_search();
+    }
+
+    /// Handle a single command line. Returns false when the engine should exit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.is_empty() {
+            return true;
+        }
+
+        match parts[0] {
+            "protover" => {
+                println!(
+                    "feature myname=\"{}\" setboard=1 usermove=1 ping=1 playother=0 san=0 \
+                     colors=0 analyze=0 sigint=0 sigterm=0 smp=1 memory=1 done=1",
+                    ENGINE_NAME
+                );
+            }
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name"
+            | "rating" | "ics" | "otim" | "draw" | "white" | "black" => {}
+            "new" => {
+                self.abort_search();
+                *self.board.lock().unwrap() = Board::starting_position();
+                self.searcher.lock().unwrap().tt.clear();
+                self.engine_side = Some(BLACK);
+                self.depth_limit = None;
+            }
+            "force" => {
+                self.abort_search();
+                self.engine_side = None;
+            }
+            "go" => {
+                self.abort_search();
+                self.engine_side = Some(self.board.lock().unwrap().side);
+                self.think();
+            }
+            "?" => self.move_now(),
+            "usermove" => match parts.get(1) {
+                Some(move_str) => self.user_move(move_str),
+                None => println!("Error (missing move): usermove"),
+            },
+            "setboard" => {
+                self.abort_search();
+                match Board::from_fen(&parts[1..].join(" ")) {
+                    Ok(board) => *self.board.lock().unwrap() = board,
+                    Err(e) => println!("tellusererror Illegal position: {}", e),
+                }
+            }
+            "time" => self.time_left_ms = parts.get(1).and_then(|t| t.parse::<u128>().ok()).map(|cs| cs * 10),
+            "level" => self.set_level(&parts[1..]),
+            "st" => self.time_per_move = parts.get(1).and_then(|t| t.parse::<u128>().ok()).map(|s| s * 1000),
+            "sd" => self.depth_limit = parts.get(1).and_then(|d| d.parse().ok()),
+            "memory" => {
+                if let Some(mb) = parts.get(1).and_then(|n| n.parse::<usize>().ok()) {
+                    self.wait_for_search();
+                    self.searcher.lock().unwrap().resize_tt(mb.max(1));
+                }
+            }
+            "cores" => {
+                if let Some(cores) = parts.get(1).and_then(|n| n.parse::<usize>().ok()) {
+                    self.wait_for_search();
+                    self.searcher.lock().unwrap().threads = cores.max(1);
+                }
+            }
+            "undo" => {
+                self.abort_search();
+                self.take_back(1);
+            }
+            "remove" => {
+                self.abort_search();
+                self.take_back(2);
+            }
+            "result" => {
+                self.abort_search();
+                self.engine_side = None;
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            // Answered at once, even while thinking or pondering
+            "ping" => println!("pong {}", parts.get(1).unwrap_or(&"")),
+            "quit" => {
+                self.abort_search();
+                return false;
+            }
+            _ => {
+                // Without the usermove feature, moves arrive bare
+                if Move::from_string(parts[0]).is_some() {
+                    self.user_move(parts[0]);
+                } else {
+                    println!("Error (unknown command): {}", parts[0]);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Apply the opponent's move and reply if it is now the engine's turn
+    fn user_move(&mut self, move_str: &str) {
+        self.abort_search();
+
+        let applied = {
+            let mut board = self.board.lock().unwrap();
+            match Move::from_string(move_str).and_then(|m| find_legal_move(&mut board, m)) {
+                Some(mov) => {
+                    board.make_move(mov);
+                    true
+                }
+                None => false,
+            }
+        };
+
+        if !applied {
+            println!("Illegal move: {}", move_str);
+            return;
+        }
+
+        if self.engine_side == Some(self.board.lock().unwrap().side) {
+            self.think();
+        }
+    }
+
+    /// Handle `level MPS BASE INC`, where BASE is minutes or "min:sec" and INC is seconds
+    fn set_level(&mut self, args: &[&str]) {
+        if args.len() < 3 {
+            println!("Error (bad arguments): level");
+            return;
+        }
+
+        self.moves_per_session = args[0].parse().unwrap_or(0);
+        self.increment_ms = (args[2].parse::<f64>().unwrap_or(0.0) * 1000.0) as u128;
+        self.time_per_move = None;
+
+        let base_secs = match args[1].split_once(':') {
+            Some((min, sec)) => min.parse::<u128>().unwrap_or(0) * 60 + sec.parse::<u128>().unwrap_or(0),
+            None => args[1].parse::<u128>().unwrap_or(0) * 60,
+        };
+        self.time_left_ms = Some(base_secs * 1000);
+    }
+
+    /// Take back `plies` half-moves, as far as the history allows
+    fn take_back(&mut self, plies: usize) {
+        let mut board = self.board.lock().unwrap();
+        for _ in 0..plies {
+            if board.history.is_empty() {
+                break;
+            }
+            board.unmake_move();
+        }
+    }
+
+    /// Build search limits from `sd`, `st`, `level` and `time`
+    fn search_limits(&self, board: &Board) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth_limit,
+            ..SearchLimits::default()
+        };
+
+        if let Some(movetime) = self.time_per_move {
+            limits.movetime = Some(movetime);
+        } else if let Some(time_left) = self.time_left_ms {
+            // `time` always reports the engine's own clock, i.e. the side to move
+            if board.side == WHITE {
+                limits.wtime = Some(time_left);
+                limits.winc = self.increment_ms;
+            } else {
+                limits.btime = Some(time_left);
+                limits.binc = self.increment_ms;
+            }
+            if self.moves_per_session > 0 {
+                let played = (board.fullmove - 1) % self.moves_per_session;
+                limits.movestogo = Some((self.moves_per_session - played) as u128);
+            }
+        }
+
+        limits
+    }
+
+    /// Start searching the current position on a background thread
+    fn think(&mut self) {
+        let mut board = self.board.lock().unwrap().clone();
+
+        if let Some(result) = game_result(&mut board) {
+            println!("{}", result);
+            return;
+        }
+
+        let limits = self.search_limits(&board);
+        let format = if self.post { InfoFormat::Cecp } else { InfoFormat::Silent };
+
+        self.stop_flag.store(false, Ordering::Relaxed);
+        self.discard_move.store(false, Ordering::Relaxed);
+
+        let searcher = Arc::clone(&self.searcher);
+        let shared_board = Arc::clone(&self.board);
+        let discard_move = Arc::clone(&self.discard_move);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let (best_move, score) = {
+                let mut searcher = searcher.lock().unwrap();
+                searcher.info_format = format;
+                let best_move = searcher.iterative_deepening(&board, limits);
+                (best_move, searcher.info.score)
+            };
+
+            if discard_move.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let mut shared = shared_board.lock().unwrap();
+            // A draw that can be claimed on the move is taken instead of playing
+            // on, unless the engine thinks it is better
+            if score <= 0 {
+                if let Some(draw) = claimable_draw(&shared) {
+                    println!("{}", result_line(&draw));
+                    return;
+                }
+            }
+
+            shared.make_move(best_move);
+            println!("move {}", best_move.to_string());
+
+            if let Some(result) = game_result(&mut shared) {
+                println!("{}", result);
+            } else if score <= 0 {
+                if let Some(draw) = claimable_draw(&shared) {
+                    println!("{}", result_line(&draw));
+                }
+            }
+        }));
+    }
+
+    /// Handle `?`: stop thinking and play the best move found so far
+    fn move_now(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    /// Stop thinking without playing a move
+    fn abort_search(&mut self) {
+        if self.search_thread.is_some() {
+            self.discard_move.store(true, Ordering::Relaxed);
+            self.stop_flag.store(true, Ordering::Relaxed);
+            self.wait_for_search();
+        }
+    }
+
+    fn wait_for_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Default for XboardEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// CECP result line if the game is over in this position. Draws that have to
+/// be claimed are left to the search, which knows whether the engine wants one.
+fn game_result(board: &mut Board) -> Option<String> {
+    automatic_outcome(board).map(|outcome| result_line(&outcome))
+}
+
+fn result_line(outcome: &GameOutcome) -> String {
+    format!("{} {{{}}}", outcome.result.as_pgn(), outcome.summary())
+}